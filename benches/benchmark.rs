@@ -3,10 +3,10 @@ extern crate criterion;
 extern crate needletail;
 
 use criterion::Criterion;
-use needletail::parse_sequence_reader;
 use needletail::sequence::Sequence;
+use needletail::{parse_sequence_bufread, parse_sequence_reader};
 use std::fs::File;
-use std::io::{Cursor, Read};
+use std::io::{BufReader, Cursor, Read};
 
 // from Bio.SeqIO import parse
 // n_total = sum([len([k for k in slid_win(i.seq, 31) if set(k).issubset({'A', 'C', 'G', 'T'})]) for i in SeqIO.parse('./tests/data/28S.fasta', 'fasta')])
@@ -126,6 +126,47 @@ fn bench_fastq_file(c: &mut Criterion) {
         });
     });
 
+    group.bench_function("Needletail (BufRead)", |bench| {
+        bench.iter(|| {
+            let reader = BufReader::new(Cursor::new(data.clone()));
+            let mut n_bases = 0;
+            parse_sequence_bufread(
+                reader,
+                |_| {},
+                |seq| {
+                    n_bases += seq.seq.len();
+                },
+            )
+            .unwrap();
+            assert_eq!(250_000, n_bases);
+        });
+    });
+
+    // run with `--features zlib-rs` to compare gzip backends
+    #[cfg(feature = "compression")]
+    group.bench_function("Needletail (gzip)", |bench| {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let gz_data = encoder.finish().unwrap();
+        bench.iter(|| {
+            let fastq_data = Cursor::new(gz_data.clone());
+            let mut n_bases = 0;
+            parse_sequence_reader(
+                fastq_data,
+                |_| {},
+                |seq| {
+                    n_bases += seq.seq.len();
+                },
+            )
+            .unwrap();
+            assert_eq!(250_000, n_bases);
+        });
+    });
+
     group.bench_function("Needletail (No Buffer)", |bench| {
         use needletail::formats::{FastqParser, RecParser};
         bench.iter(|| {
@@ -137,6 +178,14 @@ fn bench_fastq_file(c: &mut Criterion) {
             assert_eq!(250_000, n_bases);
         });
     });
+
+    group.bench_function("Needletail (Estimate Records)", |bench| {
+        use needletail::formats::estimate_fastq_records;
+        bench.iter(|| {
+            let fastq_data = Cursor::new(data.clone());
+            assert_eq!(2_000, estimate_fastq_records(fastq_data).unwrap());
+        });
+    });
 }
 
 fn bench_fasta_file(c: &mut Criterion) {
@@ -193,6 +242,32 @@ fn bench_fasta_file(c: &mut Criterion) {
         });
     });
 
+    group.bench_function("Needletail (BufRead)", |bench| {
+        bench.iter(|| {
+            let reader = BufReader::new(Cursor::new(data.clone()));
+            let mut n_bases = 0;
+            parse_sequence_bufread(
+                reader,
+                |_| {},
+                |seq| {
+                    n_bases += seq.seq.len();
+                },
+            )
+            .unwrap();
+            assert_eq!(738_580, n_bases);
+        });
+    });
+
+    group.bench_function("Needletail (Headers Only)", |bench| {
+        use needletail::formats::parse_headers;
+        bench.iter(|| {
+            let fasta_data = Cursor::new(data.clone());
+            let mut n_headers = 0;
+            parse_headers(fasta_data, |_| n_headers += 1).unwrap();
+            assert_eq!(n_headers, 570);
+        });
+    });
+
     group.bench_function("Needletail (No Buffer)", |bench| {
         use needletail::formats::{FastaParser, RecParser};
         bench.iter(|| {
@@ -5,16 +5,25 @@ use memchr::memchr;
 use crate::formats::buffer::RecParser;
 use crate::sequence::Sequence;
 use crate::sequence_record::SequenceRecord;
-use crate::util::{memchr_both_last, ParseError, ParseErrorType};
+use crate::util::{header_desc, memchr_both_last, ParseError, ParseErrorType};
 
 /// A zero-copy reference to a FASTA record in a buffer.
 #[derive(Debug)]
 pub struct FastaRecord<'a> {
     pub id: &'a [u8],
+    /// The header line exactly as it appears in the file (everything
+    /// between the `>` and the newline, including any trailing `\r`)
+    pub raw_header: &'a [u8],
     pub seq: &'a [u8],
 }
 
-impl<'a> FastaRecord<'a> {}
+impl<'a> FastaRecord<'a> {
+    /// The description after the first word of the header, if there is one
+    /// (e.g. `Homo sapiens` in `>NC_000001.11 Homo sapiens`)
+    pub fn desc(&self) -> Option<&'a [u8]> {
+        header_desc(self.id)
+    }
+}
 
 impl<'a> Sequence<'a> for FastaRecord<'a> {
     fn sequence(&self) -> &'a [u8] {
@@ -33,6 +42,7 @@ pub struct FastaParser<'a> {
     buf: &'a [u8],
     last: bool,
     pos: usize,
+    header_char: u8,
 }
 
 impl<'a> FastaParser<'a> {
@@ -46,7 +56,14 @@ impl<'a> FastaParser<'a> {
             .context(context));
         }
 
-        Ok(FastaParser { buf, last, pos: 0 })
+        Ok(FastaParser::from_buffer(buf, last))
+    }
+
+    /// Split records at lines starting with `header_char` instead of `>`
+    /// (for FASTA-like formats with a different record marker)
+    pub fn header_char(mut self, header_char: u8) -> Self {
+        self.header_char = header_char;
+        self
     }
 }
 
@@ -65,18 +82,24 @@ impl<'a> Iterator for FastaParser<'a> {
             Some(i) => id_end = i + 1,
             None => return None,
         };
-        let mut id = &buf[1..id_end - 1];
+        let raw_header = &buf[1..id_end - 1];
+        let mut id = raw_header;
         if !id.is_empty() && id[id.len() - 1] == b'\r' {
             id = &id[..id.len() - 1];
         }
 
-        let seq_end;
-        match (memchr_both_last(b'\n', b'>', &buf[id_end..]), self.last) {
-            (Some(i), _) => seq_end = id_end + i + 1,
-            (None, true) => seq_end = buf.len(),
+        // start from the header's line ending so a record starting on the
+        // very next line is found too (leaving this one with an empty
+        // sequence)
+        let (seq_end, at_next_record) = match (
+            memchr_both_last(b'\n', self.header_char, &buf[id_end - 1..]),
+            self.last,
+        ) {
+            (Some(i), _) => (id_end + i, true),
+            (None, true) => (buf.len(), false),
             (None, false) => return None,
         };
-        if id_end == seq_end {
+        if id_end == seq_end && !at_next_record {
             let context = String::from_utf8_lossy(id);
             return Some(Err(ParseError::new(
                 "Sequence completely empty",
@@ -93,7 +116,11 @@ impl<'a> Iterator for FastaParser<'a> {
         }
 
         self.pos += seq_end;
-        Some(Ok(FastaRecord { id, seq }))
+        Some(Ok(FastaRecord {
+            id,
+            raw_header,
+            seq,
+        }))
     }
 }
 
@@ -101,7 +128,12 @@ impl<'a> RecParser<'a> for FastaParser<'a> {
     type Header = ();
 
     fn from_buffer(buf: &[u8], last: bool) -> FastaParser {
-        FastaParser { buf, last, pos: 0 }
+        FastaParser {
+            buf,
+            last,
+            pos: 0,
+            header_char: b'>',
+        }
     }
 
     fn header(&mut self) -> Result<Self::Header, ParseError> {
@@ -410,6 +442,64 @@ mod test {
         assert_eq!(res, Ok(()));
     }
 
+    #[test]
+    fn test_blank_lines_in_record() {
+        // blank lines inside of a record are skipped over and the sequence
+        // on either side of them is joined together
+        let mut i = 0;
+        let res = parse_sequence_reader(
+            seq(b">x\nAC\n\nGT\n>y\r\nTT\r\n\r\n\r\nGG\r\n\n"),
+            |_| {},
+            |seq| {
+                match i {
+                    0 => {
+                        assert_eq!(&seq.id[..], b"x");
+                        assert_eq!(&seq.seq[..], b"ACGT");
+                    }
+                    1 => {
+                        assert_eq!(&seq.id[..], b"y");
+                        assert_eq!(&seq.seq[..], b"TTGG");
+                    }
+                    _ => unreachable!("Too many records"),
+                }
+                i += 1;
+            },
+        );
+        assert_eq!(i, 2);
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_record_boundaries() {
+        // a `>` only starts a record at the start of a line
+        let mut reader =
+            FastaParser::new(b">a desc=\"x > y\" >b\nAC>GT\n>c>d\nTT\n", true).unwrap();
+        let rec = reader.next().unwrap().unwrap();
+        assert_eq!(rec.id, b"a desc=\"x > y\" >b");
+        assert_eq!(rec.seq, b"AC>GT");
+        let rec = reader.next().unwrap().unwrap();
+        assert_eq!(rec.id, b"c>d");
+        assert_eq!(rec.seq, b"TT");
+        assert!(reader.next().is_none());
+
+        // a record right after a header isn't part of the first sequence,
+        // which is just empty (like it is with a blank line in between)
+        for data in &[
+            &b">a\n>b\nAC\n"[..],
+            b">a\n\n>b\nAC\n",
+            b">a\r\n>b\r\nAC\r\n",
+        ] {
+            let mut reader = FastaParser::new(data, true).unwrap();
+            let rec = reader.next().unwrap().unwrap();
+            assert_eq!(rec.id, b"a");
+            assert_eq!(rec.seq, b"");
+            let rec = reader.next().unwrap().unwrap();
+            assert_eq!(rec.id, b"b");
+            assert_eq!(rec.seq, b"AC");
+            assert!(reader.next().is_none());
+        }
+    }
+
     #[test]
     fn test_reader() {
         let mut reader = FastaParser::new(b">test\nACGT", true).unwrap();
@@ -420,4 +510,92 @@ mod test {
         let mut reader = FastaParser::new(b">test", true).unwrap();
         assert!(reader.next().is_none(), "Incomplete record returns None");
     }
+
+    #[test]
+    fn test_raw_header() {
+        let mut reader = FastaParser::new(b">test  a\tb \t c\r\nACGT\n>t\nA", true).unwrap();
+        let rec = reader.next().unwrap().unwrap();
+        assert_eq!(rec.raw_header, b"test  a\tb \t c\r");
+        assert_eq!(rec.id, b"test  a\tb \t c");
+        assert_eq!(rec.desc(), Some(&b"a\tb \t c"[..]));
+        let rec = reader.next().unwrap().unwrap();
+        assert_eq!(rec.raw_header, b"t");
+        assert_eq!(rec.desc(), None);
+    }
+
+    #[test]
+    fn test_desc_is_lazy() {
+        use crate::util::HEADER_DESC_CALLS;
+
+        let data = b">a first\nACGT\n>b second\r\nAC\nGT\n>c\nA\n".repeat(100);
+        let n_calls = || HEADER_DESC_CALLS.with(|calls| calls.get());
+        let start = n_calls();
+        let mut header_bytes = 0;
+        for rec in FastaParser::new(&data, true).unwrap() {
+            header_bytes += rec.unwrap().raw_header.len();
+        }
+        assert_eq!(header_bytes, 100 * (7 + 9 + 1));
+        // only looking at the raw headers never splits them up
+        assert_eq!(n_calls(), start);
+
+        let rec = FastaParser::new(&data, true)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(rec.desc(), Some(&b"first"[..]));
+        assert_eq!(n_calls(), start + 1);
+    }
+
+    #[test]
+    fn test_header_char() {
+        use crate::formats::{parse_sequence_reader_with_options, ParseOptions};
+
+        let data = b";a first\nAC>GT\nTT\n;b\nGGCC\n";
+        let options = ParseOptions::new().header_char(Some(b';'));
+        let mut recs = Vec::new();
+        parse_sequence_reader_with_options(
+            seq(data),
+            &options,
+            |file_type| assert_eq!(file_type, "FASTA"),
+            |rec| recs.push((rec.id.into_owned(), rec.seq.into_owned())),
+        )
+        .unwrap();
+        assert_eq!(
+            recs,
+            vec![
+                (b"a first".to_vec(), b"AC>GTTT".to_vec()),
+                (b"b".to_vec(), b"GGCC".to_vec())
+            ]
+        );
+
+        let mut reader = FastaParser::new(b">a\nAC\n;b\nGG", true)
+            .unwrap()
+            .header_char(b';');
+        assert_eq!(reader.next().unwrap().unwrap().seq, b"AC");
+        assert_eq!(reader.next().unwrap().unwrap().id, b"b");
+
+        let res = parse_sequence_reader_with_options(seq(b">a\nACGT\n"), &options, |_| {}, |_| {});
+        assert_eq!(res.unwrap_err().error_type, ParseErrorType::InvalidHeader);
+    }
+
+    #[test]
+    fn test_raw_sequences() {
+        use crate::formats::{parse_sequence_reader_with_options, ParseOptions};
+        use std::borrow::Cow;
+
+        let options = ParseOptions::new().raw(true);
+        let mut seqs = Vec::new();
+        parse_sequence_reader_with_options(
+            seq(b">a\nACGT\r\nAC\n\nGT\r\n>b\nTT\n"),
+            &options,
+            |_| {},
+            |rec| {
+                assert!(matches!(rec.seq, Cow::Borrowed(_)));
+                seqs.push(rec.seq.into_owned());
+            },
+        )
+        .unwrap();
+        assert_eq!(seqs, vec![b"ACGT\r\nAC\n\nGT".to_vec(), b"TT".to_vec()]);
+    }
 }
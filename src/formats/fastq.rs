@@ -1,22 +1,75 @@
 use std::cmp::min;
+use std::io::Read;
 
-use memchr::memchr;
+use memchr::{memchr, memchr_iter};
 
 use crate::formats::buffer::RecParser;
 use crate::formats::fasta::check_end;
+use crate::formats::BUF_SIZE;
 use crate::sequence::Sequence;
 use crate::sequence_record::SequenceRecord;
-use crate::util::{memchr_both, ParseError, ParseErrorType};
+use crate::util::{header_desc, memchr_both, ParseError, ParseErrorType};
 
 /// A zero-copy reference to a FASTQ record in a buffer.
 #[derive(Debug)]
 pub struct FastqRecord<'a> {
     pub id: &'a [u8],
+    /// The header line exactly as it appears in the file (everything
+    /// between the `@` and the newline, including any trailing `\r`)
+    pub raw_header: &'a [u8],
     pub seq: &'a [u8],
     pub id2: &'a [u8],
     pub qual: &'a [u8],
 }
 
+impl<'a> FastqRecord<'a> {
+    /// The description after the first word of the header, if there is one
+    /// (see `FastaRecord::desc`)
+    pub fn desc(&self) -> Option<&'a [u8]> {
+        header_desc(self.id)
+    }
+
+    /// Check that the record is exactly four lines long: the header, the
+    /// sequence, a `+` line (that's either empty or repeats the header) and
+    /// the quality scores. This catches corrupted records that the parser
+    /// would otherwise accept (e.g. by reading a missing `+` line's sequence
+    /// and quality into the previous line). Every quality score also has to
+    /// be printable ASCII (`!` to `~`), to catch binary corruption.
+    pub fn check_strict(&self) -> Result<(), ParseError> {
+        let line_error = |line: usize, msg: &str| {
+            ParseError::new(
+                format!("Line {} of the record {}", line, msg),
+                ParseErrorType::InvalidRecord,
+            )
+            .context(String::from_utf8_lossy(self.id))
+        };
+        if memchr(b'\n', self.seq).is_some() {
+            return Err(line_error(3, "should start with '+'"));
+        }
+        // id2 is the whole `+` line
+        let id2 = self.id2.strip_prefix(b"+").unwrap_or(self.id2);
+        let id2 = id2.strip_suffix(b"\r").unwrap_or(id2);
+        if !id2.is_empty() && id2 != self.id {
+            return Err(line_error(3, "doesn't match the header"));
+        }
+        if memchr(b'\n', self.qual).is_some() {
+            return Err(line_error(4, "has more than one line of quality scores"));
+        }
+        if let Some(pos) = self.qual.iter().position(|q| !(0x21..=0x7e).contains(q)) {
+            return Err(ParseError::new(
+                format!(
+                    "Quality score at position {} isn't printable ASCII ('{}')",
+                    pos,
+                    self.qual[pos].escape_ascii()
+                ),
+                ParseErrorType::InvalidQuality,
+            )
+            .context(String::from_utf8_lossy(self.id)));
+        }
+        Ok(())
+    }
+}
+
 impl<'a> Sequence<'a> for FastqRecord<'a> {
     fn sequence(&self) -> &'a [u8] {
         self.seq
@@ -74,7 +127,8 @@ impl<'a> Iterator for FastqParser<'a> {
             Some(i) => id_end = i + 1,
             None => return None,
         };
-        let mut id = &buf[1..id_end - 1];
+        let raw_header = &buf[1..id_end - 1];
+        let mut id = raw_header;
 
         let seq_end;
         match memchr_both(b'\n', b'+', &buf[id_end..]) {
@@ -146,7 +200,13 @@ impl<'a> Iterator for FastqParser<'a> {
         }
 
         self.pos += buffer_used;
-        Some(Ok(FastqRecord { id, seq, id2, qual }))
+        Some(Ok(FastqRecord {
+            id,
+            raw_header,
+            seq,
+            id2,
+            qual,
+        }))
     }
 }
 
@@ -170,13 +230,40 @@ impl<'a> RecParser<'a> for FastqParser<'a> {
     }
 }
 
+/// Quickly estimate the number of records in a FASTQ stream by counting its
+/// lines (without parsing them), e.g. for preallocating or reporting progress.
+///
+/// This is only an estimate: it assumes every record takes up exactly four
+/// lines so it will be wrong for FASTQs with wrapped sequences or qualities.
+/// The stream is read as-is, so compressed data needs to be decompressed
+/// before being passed in.
+pub fn estimate_fastq_records<R: Read>(mut reader: R) -> Result<u64, ParseError> {
+    let mut buf = vec![0; BUF_SIZE];
+    let mut lines: u64 = 0;
+    let mut last_byte = b'\n';
+    loop {
+        let amt_read = reader.read(&mut buf)?;
+        if amt_read == 0 {
+            break;
+        }
+        lines += memchr_iter(b'\n', &buf[..amt_read]).count() as u64;
+        last_byte = buf[amt_read - 1];
+    }
+    // count a final line even if it's not terminated by a newline
+    if last_byte != b'\n' {
+        lines += 1;
+    }
+    Ok(lines / 4)
+}
+
 #[cfg(test)]
 mod test {
+    use std::fs::File;
     use std::io::Cursor;
 
-    use super::FastqParser;
+    use super::{estimate_fastq_records, FastqParser};
     use crate::formats::buffer::{RecBuffer, RecParser};
-    use crate::formats::parse_sequence_reader;
+    use crate::formats::{parse_sequence_reader, parse_sequence_reader_with_options, ParseOptions};
     use crate::util::ParseErrorType;
 
     fn seq(s: &[u8]) -> Cursor<&[u8]> {
@@ -243,6 +330,68 @@ mod test {
         assert_eq!(res, Ok(()));
     }
 
+    #[test]
+    fn test_raw_header() {
+        use crate::util::HEADER_DESC_CALLS;
+
+        let mut fp = FastqParser::new(b"@test  a\tb \t c\r\nA\r\n+\r\nI\r\n", true).unwrap();
+        let rec = fp.next().unwrap().unwrap();
+        assert_eq!(rec.raw_header, b"test  a\tb \t c\r");
+        assert_eq!(rec.id, b"test  a\tb \t c");
+        assert_eq!(rec.desc(), Some(&b"a\tb \t c"[..]));
+
+        // and headers are only split up when the description's asked for
+        let n_calls = || HEADER_DESC_CALLS.with(|calls| calls.get());
+        let start = n_calls();
+        let data = b"@r1 x\nAC\n+\nII\n@r2 y\nGT\n+\nII\n";
+        let raw_headers: Vec<_> = FastqParser::new(data, true)
+            .unwrap()
+            .map(|rec| rec.unwrap().raw_header)
+            .collect();
+        assert_eq!(raw_headers, [&b"r1 x"[..], b"r2 y"]);
+        assert_eq!(n_calls(), start);
+    }
+
+    #[test]
+    fn test_strict() {
+        let fq = b"@A\nAC\n+\nII\n@B\r\nAC\r\n+B\r\nII\r\n";
+        let options = ParseOptions::new().strict_fastq(true);
+        let mut n_records = 0;
+        let res = parse_sequence_reader_with_options(seq(fq), &options, |_| {}, |_| n_records += 1);
+        assert_eq!(res, Ok(()));
+        assert_eq!(n_records, 2);
+
+        // the first record is missing its `+` line so normally the parser
+        // sucks the next record into it
+        let fq = b"@A\nAC\nII\n@B\nAC\n+\nIIIIIIIIIII\n";
+        let mut n_records = 0;
+        let res = parse_sequence_reader(seq(fq), |_| {}, |_| n_records += 1);
+        assert_eq!(res, Ok(()));
+        assert_eq!(n_records, 1);
+
+        let e = parse_sequence_reader_with_options(seq(fq), &options, |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::InvalidRecord);
+        assert_eq!(e.record, 1);
+        assert_eq!(e.msg, "Line 3 of the record should start with '+'");
+        assert_eq!(e.context, "A");
+
+        let fq = b"@A\nAC\n+\nII\n@B\nAC\n+C\nII\n";
+        let e = parse_sequence_reader_with_options(seq(fq), &options, |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.record, 2);
+        assert_eq!(e.msg, "Line 3 of the record doesn't match the header");
+
+        // a control character in the quality scores
+        let fq = b"@A\nACGT\n+\nII\x07I\n";
+        assert!(parse_sequence_reader(seq(fq), |_| {}, |_| {}).is_ok());
+        let e = parse_sequence_reader_with_options(seq(fq), &options, |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::InvalidQuality);
+        assert_eq!(
+            e.msg,
+            "Quality score at position 2 isn't printable ASCII ('\\x07')"
+        );
+        assert_eq!(e.record, 1);
+    }
+
     #[test]
     fn test_premature_endings() {
         let test = b"@test\nACGT\n+\nIII\n";
@@ -421,4 +570,19 @@ mod test {
 
         // TODO: refill and check for the last record
     }
+
+    #[test]
+    fn test_estimate_fastq_records() {
+        let mut n_records = 0;
+        let file = File::open("./tests/data/PRJNA271013_head.fq").unwrap();
+        parse_sequence_reader(file, |_| {}, |_| n_records += 1).unwrap();
+
+        let file = File::open("./tests/data/PRJNA271013_head.fq").unwrap();
+        assert_eq!(estimate_fastq_records(file).unwrap(), n_records);
+
+        // the last line doesn't need a newline
+        let test = b"@test\nAGCT\n+\n~~a!\n@test2\nTGCA\n+\nWUI9";
+        assert_eq!(estimate_fastq_records(seq(test)).unwrap(), 2);
+        assert_eq!(estimate_fastq_records(seq(b"")).unwrap(), 0);
+    }
 }
@@ -0,0 +1,229 @@
+use std::io::Read;
+
+use memchr::memchr;
+
+use crate::formats::{decompress, detect_file_type, is_blank, BUF_SIZE};
+use crate::util::{header_id, ParseError, ParseErrorType};
+
+/// Scan through a FASTA stream passing every header (without the `>` or
+/// line ending) to `header_callback`, skipping over the sequences as quickly
+/// as possible. This is much faster than parsing the whole file when only
+/// the headers are needed (e.g. for cataloging what's in a big file).
+///
+/// Compressed streams are transparently decompressed. Because the sequences
+/// aren't parsed, malformed records aren't caught like they would be by
+/// `parse_sequence_reader`.
+///
+/// ```
+/// use needletail::formats::parse_headers;
+///
+/// let mut headers = Vec::new();
+/// parse_headers(&b">a first\nACGT\n>b\nTT\nGG\n"[..], |h| headers.push(h.to_vec())).unwrap();
+/// assert_eq!(headers, vec![b"a first".to_vec(), b"b".to_vec()]);
+/// ```
+pub fn parse_headers<R, F>(reader: R, mut header_callback: F) -> Result<(), ParseError>
+where
+    R: Read,
+    F: FnMut(&[u8]),
+{
+    let (mut reader, mut buf) = decompress(reader)?;
+    if buf.iter().all(u8::is_ascii_whitespace) && is_blank(&mut reader, &mut buf)? {
+        return Ok(());
+    }
+    if detect_file_type(&buf)? != "FASTA" {
+        return Err(ParseError::new(
+            "Headers can only be scanned out of FASTA files",
+            ParseErrorType::Invalid,
+        ));
+    }
+
+    // a header that's split across reads
+    let mut header = Vec::new();
+    let mut in_header = false;
+    let mut at_line_start = true;
+    loop {
+        let mut pos = 0;
+        while pos < buf.len() {
+            if in_header {
+                match memchr(b'\n', &buf[pos..]) {
+                    Some(i) => {
+                        header.extend_from_slice(&buf[pos..pos + i]);
+                        if header.last() == Some(&b'\r') {
+                            header.pop();
+                        }
+                        header_callback(&header);
+                        header.clear();
+                        in_header = false;
+                        at_line_start = true;
+                        pos += i + 1;
+                    }
+                    None => {
+                        header.extend_from_slice(&buf[pos..]);
+                        pos = buf.len();
+                    }
+                }
+            } else if at_line_start && buf[pos] == b'>' {
+                in_header = true;
+                pos += 1;
+            } else {
+                // skip ahead to the next `>` and check if it starts a line
+                match memchr(b'>', &buf[pos..]) {
+                    Some(i) => {
+                        if i > 0 {
+                            at_line_start = buf[pos + i - 1] == b'\n';
+                        }
+                        pos += if at_line_start { i } else { i + 1 };
+                    }
+                    None => {
+                        at_line_start = buf.last() == Some(&b'\n');
+                        pos = buf.len();
+                    }
+                }
+            }
+        }
+
+        buf.resize(BUF_SIZE, 0);
+        let amt_read = reader.read(&mut buf)?;
+        if amt_read == 0 {
+            break;
+        }
+        buf.truncate(amt_read);
+    }
+    // the last header in the file (with no sequence or line ending)
+    if in_header {
+        if header.last() == Some(&b'\r') {
+            header.pop();
+        }
+        header_callback(&header);
+    }
+    Ok(())
+}
+
+/// The number of fields following each NCBI FASTA identifier tag (e.g.
+/// `gb|accession|locus`)
+const NCBI_TAGS: &[(&[u8], usize)] = &[
+    (b"gi", 1),
+    (b"lcl", 1),
+    (b"bbs", 1),
+    (b"bbm", 1),
+    (b"gim", 1),
+    (b"ref", 2),
+    (b"gb", 2),
+    (b"emb", 2),
+    (b"dbj", 2),
+    (b"pir", 2),
+    (b"prf", 2),
+    (b"sp", 2),
+    (b"tr", 2),
+    (b"tpg", 2),
+    (b"tpe", 2),
+    (b"tpd", 2),
+    (b"gnl", 2),
+    (b"pdb", 2),
+    (b"pat", 3),
+];
+
+/// Split an NCBI-style identifier (like `gi|12345|ref|NC_000001.11|`) into
+/// `(database tag, accession)` pairs. Only the first field after each tag
+/// is returned (so e.g. the locus name after a GenBank accession is left
+/// out). Anything after the first whitespace is ignored and an empty list
+/// is returned if the id doesn't start with a known tag.
+///
+/// ```
+/// use needletail::formats::parse_ncbi_header;
+///
+/// let fields = parse_ncbi_header(b"gi|12345|ref|NC_000001.11| Homo sapiens");
+/// assert_eq!(fields, vec![(&b"gi"[..], &b"12345"[..]), (b"ref", b"NC_000001.11")]);
+/// ```
+pub fn parse_ncbi_header(id: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let id = header_id(id);
+    let mut fields = id.split(|c| *c == b'|');
+    let mut pairs = Vec::new();
+    while let Some(tag) = fields.next() {
+        let n_fields = match NCBI_TAGS.iter().find(|(t, _)| *t == tag) {
+            Some((_, n)) => *n,
+            None => break,
+        };
+        match fields.next() {
+            Some(accession) => pairs.push((tag, accession)),
+            None => break,
+        }
+        for _ in 1..n_fields {
+            fields.next();
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::io::Cursor;
+
+    use super::{parse_headers, parse_ncbi_header};
+    use crate::formats::parse_sequence_reader;
+
+    #[test]
+    fn test_parse_headers() {
+        let data = b">a first\r\nAC>GT\r\n>b\n\n>\n>c\nTT\nG>G\n>d";
+        let mut headers = Vec::new();
+        parse_headers(Cursor::new(&data[..]), |h| headers.push(h.to_vec())).unwrap();
+        assert_eq!(
+            headers,
+            vec![
+                b"a first".to_vec(),
+                b"b".to_vec(),
+                b"".to_vec(),
+                b"c".to_vec(),
+                b"d".to_vec()
+            ]
+        );
+
+        assert!(parse_headers(Cursor::new(&b""[..]), |_| panic!("No headers")).is_ok());
+        assert!(parse_headers(Cursor::new(&b"@a\nA\n+\nI\n"[..]), |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_parse_headers_matches_parsing() {
+        // big enough that headers straddle reads
+        let mut expected = Vec::new();
+        parse_sequence_reader(
+            File::open("./tests/data/28S.fasta").unwrap(),
+            |_| {},
+            |rec| expected.push(rec.id.to_vec()),
+        )
+        .unwrap();
+
+        let mut headers = Vec::new();
+        parse_headers(File::open("./tests/data/28S.fasta").unwrap(), |h| {
+            headers.push(h.to_vec())
+        })
+        .unwrap();
+        assert_eq!(headers, expected);
+    }
+
+    #[test]
+    fn test_parse_ncbi_header() {
+        assert_eq!(
+            parse_ncbi_header(b"gi|568815597|ref|NC_000001.11|"),
+            vec![(&b"gi"[..], &b"568815597"[..]), (b"ref", b"NC_000001.11")]
+        );
+        // the locus name after an accession is skipped
+        assert_eq!(
+            parse_ncbi_header(b"gi|2|gb|AAA12345.1|LOCUS|emb|CAA1.1|\tdesc"),
+            vec![
+                (&b"gi"[..], &b"2"[..]),
+                (b"gb", b"AAA12345.1"),
+                (b"emb", b"CAA1.1")
+            ]
+        );
+        assert_eq!(
+            parse_ncbi_header(b"sp|P69905|HBA_HUMAN"),
+            vec![(&b"sp"[..], &b"P69905"[..])]
+        );
+
+        assert!(parse_ncbi_header(b"read1 length=150").is_empty());
+        assert!(parse_ncbi_header(b"chr1|part2").is_empty());
+        assert!(parse_ncbi_header(b"").is_empty());
+    }
+}
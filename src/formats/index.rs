@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::str;
+
+use crate::sequence::Sequence;
+use crate::sequence_record::SequenceRecord;
+use crate::util::{header_id, ParseError, ParseErrorType};
+
+/// A single entry of a FASTA index; these are the same fields as in a line
+/// of a samtools `.fai` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FastaIndexEntry {
+    /// The record name (the header up to the first whitespace)
+    pub name: Vec<u8>,
+    /// The number of bases in the sequence
+    pub length: u64,
+    /// The byte offset of the first base of the sequence in the file
+    pub offset: u64,
+    /// The number of bases on each line
+    pub line_bases: u64,
+    /// The number of bytes on each line (including the line ending)
+    pub line_width: u64,
+}
+
+impl FastaIndexEntry {
+    /// The byte offset in the file of the base at `pos` (0-based)
+    fn byte_offset(&self, pos: u64) -> u64 {
+        self.offset + (pos / self.line_bases) * self.line_width + pos % self.line_bases
+    }
+
+    /// Read the bases from `start` to `end` (0-based, half-open) out of the
+    /// file, without the line endings
+    fn read_bases<R>(&self, reader: &mut R, start: u64, end: u64) -> Result<Vec<u8>, ParseError>
+    where
+        R: Read + Seek,
+    {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        let start = self.byte_offset(start);
+        let end = self.byte_offset(end - 1) + 1;
+        let mut raw = vec![0; (end - start) as usize];
+        reader.seek(SeekFrom::Start(start))?;
+        reader.read_exact(&mut raw)?;
+        Ok(raw.strip_returns().into_owned())
+    }
+}
+
+/// An index over an uncompressed FASTA file allowing random access to
+/// subsequences of its records (e.g. for extracting BED regions).
+#[derive(Clone, Debug, Default)]
+pub struct FastaIndex {
+    entries: Vec<FastaIndexEntry>,
+    names: HashMap<Vec<u8>, usize>,
+}
+
+impl FastaIndex {
+    fn push(&mut self, entry: FastaIndexEntry) -> Result<(), ParseError> {
+        if self.names.contains_key(&entry.name) {
+            let context = String::from_utf8_lossy(&entry.name);
+            return Err(ParseError::new(
+                "Duplicate record name in index",
+                ParseErrorType::InvalidHeader,
+            )
+            .record(self.entries.len() + 1)
+            .context(context));
+        }
+        self.names.insert(entry.name.clone(), self.entries.len());
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Build an index by scanning through an (uncompressed) FASTA file.
+    ///
+    /// Like samtools, every line of a record except the last must have the
+    /// same length for the record to be indexable.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, ParseError> {
+        let mut reader = BufReader::new(reader);
+        let mut index = FastaIndex::default();
+        let mut line = Vec::new();
+        let mut pos: u64 = 0;
+        let mut current: Option<FastaIndexEntry> = None;
+        // set once we've seen a line shorter than the others in the record
+        let mut short_line = false;
+
+        loop {
+            line.clear();
+            let amt_read = reader.read_until(b'\n', &mut line)? as u64;
+            if amt_read == 0 {
+                break;
+            }
+            pos += amt_read;
+
+            if line[0] == b'>' {
+                if let Some(entry) = current.take() {
+                    index.push(entry)?;
+                }
+                let name = header_id(&line[1..]).to_vec();
+                current = Some(FastaIndexEntry {
+                    name,
+                    length: 0,
+                    offset: pos,
+                    line_bases: 0,
+                    line_width: 0,
+                });
+                short_line = false;
+                continue;
+            }
+
+            let entry = match current.as_mut() {
+                Some(entry) => entry,
+                None => {
+                    let context = String::from_utf8_lossy(&line[..line.len().min(64)]);
+                    return Err(ParseError::new(
+                        "FASTA record must start with '>'",
+                        ParseErrorType::InvalidHeader,
+                    )
+                    .context(context));
+                }
+            };
+            let bases = line.strip_returns().len() as u64;
+            if bases == 0 {
+                // blank lines are only allowed at the ends of records
+                short_line = true;
+                continue;
+            }
+            if short_line || (entry.line_bases != 0 && bases > entry.line_bases) {
+                let context = String::from_utf8_lossy(&entry.name);
+                return Err(ParseError::new(
+                    "Record has lines of differing lengths and can not be indexed",
+                    ParseErrorType::InvalidRecord,
+                )
+                .record(index.entries.len() + 1)
+                .context(context));
+            }
+            if entry.line_bases == 0 {
+                entry.line_bases = bases;
+                entry.line_width = amt_read;
+            } else if bases != entry.line_bases || amt_read != entry.line_width {
+                // a shorter line (or one missing its line ending) has to be
+                // the last line in the record
+                short_line = true;
+            }
+            entry.length += bases;
+        }
+        if let Some(entry) = current.take() {
+            index.push(entry)?;
+        }
+        Ok(index)
+    }
+
+    /// Read an index from a samtools-style `.fai` file.
+    pub fn from_fai<R: Read>(reader: R) -> Result<Self, ParseError> {
+        let mut index = FastaIndex::default();
+        for (line_num, line) in BufReader::new(reader).split(b'\n').enumerate() {
+            let line = line?;
+            let line = line.strip_returns();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&[u8]> = line.split(|c| *c == b'\t').collect();
+            if fields.len() < 5 {
+                return Err(ParseError::new(
+                    "Index line must have at least 5 fields",
+                    ParseErrorType::Invalid,
+                )
+                .record(line_num + 1)
+                .context(String::from_utf8_lossy(&line)));
+            }
+            let mut numbers = [0u64; 4];
+            for (n, field) in numbers.iter_mut().zip(&fields[1..5]) {
+                *n = str::from_utf8(field)?.parse().map_err(|_| {
+                    ParseError::new("Index field is not a number", ParseErrorType::Invalid)
+                        .record(line_num + 1)
+                        .context(String::from_utf8_lossy(field))
+                })?;
+            }
+            let [length, offset, line_bases, line_width] = numbers;
+            // these would make `byte_offset` divide by zero or read the
+            // wrong bytes
+            if (length > 0 && line_bases == 0) || line_width < line_bases {
+                return Err(ParseError::new(
+                    "Index line has an invalid line length",
+                    ParseErrorType::Invalid,
+                )
+                .record(line_num + 1)
+                .context(String::from_utf8_lossy(&line)));
+            }
+            index.push(FastaIndexEntry {
+                name: fields[0].to_vec(),
+                length,
+                offset,
+                line_bases,
+                line_width,
+            })?;
+        }
+        Ok(index)
+    }
+
+    /// Look up the entry for a record by name
+    pub fn get(&self, name: &[u8]) -> Option<&FastaIndexEntry> {
+        self.names.get(name).map(|i| &self.entries[*i])
+    }
+
+    /// All of the entries in the index (in file order)
+    pub fn entries(&self) -> &[FastaIndexEntry] {
+        &self.entries
+    }
+}
+
+/// The strand a region should be extracted from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+/// A region of a record using BED conventions (0-based, half-open)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub name: Vec<u8>,
+    pub start: u64,
+    pub end: u64,
+    pub strand: Strand,
+}
+
+impl Region {
+    pub fn new<N>(name: N, start: u64, end: u64, strand: Strand) -> Self
+    where
+        N: Into<Vec<u8>>,
+    {
+        Region {
+            name: name.into(),
+            start,
+            end,
+            strand,
+        }
+    }
+}
+
+/// Extract a region out of an indexed FASTA file. Regions on the reverse
+/// strand are reverse complemented. The returned record is named like
+/// `bedtools getfasta -s` would (e.g. `chr1:10-20(-)`).
+pub fn fetch_region<R>(
+    index: &FastaIndex,
+    reader: &mut R,
+    region: &Region,
+) -> Result<SequenceRecord<'static>, ParseError>
+where
+    R: Read + Seek,
+{
+    let strand = match region.strand {
+        Strand::Forward => "+",
+        Strand::Reverse => "-",
+    };
+    let id = format!(
+        "{}:{}-{}({})",
+        String::from_utf8_lossy(&region.name),
+        region.start,
+        region.end,
+        strand
+    );
+
+    let entry = index.get(&region.name).ok_or_else(|| {
+        ParseError::new("Record not found in index", ParseErrorType::Invalid).context(&id)
+    })?;
+    if region.start > region.end {
+        return Err(
+            ParseError::new("Region start is after its end", ParseErrorType::Invalid).context(&id),
+        );
+    }
+    if region.end > entry.length {
+        return Err(ParseError::new(
+            format!(
+                "Region end is past the end of the record ({} bases)",
+                entry.length
+            ),
+            ParseErrorType::Invalid,
+        )
+        .context(&id));
+    }
+
+    let mut seq = entry.read_bases(reader, region.start, region.end)?;
+    if region.strand == Strand::Reverse {
+        seq = seq.reverse_complement();
+    }
+    Ok(SequenceRecord::new(
+        id.into_bytes().into(),
+        seq.into(),
+        None,
+    ))
+}
+
+/// Extract whole records out of an indexed FASTA file by name (like
+/// `samtools faidx -r`), in the order they're listed in `names`. The
+/// returned records are named with just the record name, since the index
+/// doesn't keep the rest of the header. A name that's not in the index is
+/// an error.
+pub fn extract_named<R>(
+    index: &FastaIndex,
+    reader: &mut R,
+    names: &[&[u8]],
+) -> Result<Vec<SequenceRecord<'static>>, ParseError>
+where
+    R: Read + Seek,
+{
+    names
+        .iter()
+        .map(|name| {
+            let entry = index.get(name).ok_or_else(|| {
+                ParseError::new("Record not found in index", ParseErrorType::Invalid)
+                    .context(String::from_utf8_lossy(name))
+            })?;
+            let seq = entry.read_bases(reader, 0, entry.length)?;
+            Ok(SequenceRecord::new_owned(*name, seq, None))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::{extract_named, fetch_region, FastaIndex, Region, Strand};
+    use crate::util::ParseErrorType;
+
+    const FASTA: &[u8] = b">chr1 first\nACGT\nTTGA\nCC\n>chr2\r\nGGGG\r\nAA\r\n";
+
+    #[test]
+    fn test_build_index() {
+        let index = FastaIndex::from_reader(FASTA).unwrap();
+        assert_eq!(index.entries().len(), 2);
+
+        let chr1 = index.get(b"chr1").unwrap();
+        assert_eq!(chr1.length, 10);
+        assert_eq!(chr1.offset, 12);
+        assert_eq!(chr1.line_bases, 4);
+        assert_eq!(chr1.line_width, 5);
+
+        let chr2 = index.get(b"chr2").unwrap();
+        assert_eq!(chr2.length, 6);
+        assert_eq!(chr2.offset, 32);
+        assert_eq!(chr2.line_bases, 4);
+        assert_eq!(chr2.line_width, 6);
+
+        let fai = FastaIndex::from_fai(&b"chr1\t10\t12\t4\t5\nchr2\t6\t32\t4\t6\n"[..]).unwrap();
+        assert_eq!(fai.entries(), index.entries());
+
+        // no bases per line, or lines shorter than their bases
+        let e = FastaIndex::from_fai(&b"ok\t0\t3\t0\t0\nchr\t10\t0\t0\t0\n"[..]).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::Invalid);
+        assert_eq!(e.record, 2);
+        assert!(FastaIndex::from_fai(&b"chr\t10\t0\t5\t4\n"[..]).is_err());
+
+        let res = FastaIndex::from_reader(&b">bad\nACGT\nAC\nACGT\n"[..]);
+        assert_eq!(res.unwrap_err().error_type, ParseErrorType::InvalidRecord);
+    }
+
+    #[test]
+    fn test_fetch_region() {
+        let index = FastaIndex::from_reader(FASTA).unwrap();
+        let mut reader = Cursor::new(FASTA);
+
+        // spans a line break
+        let region = Region::new("chr1", 2, 7, Strand::Forward);
+        let rec = fetch_region(&index, &mut reader, &region).unwrap();
+        assert_eq!(&rec.id[..], b"chr1:2-7(+)");
+        assert_eq!(&rec.seq[..], b"GTTTG");
+
+        let region = Region::new("chr1", 2, 7, Strand::Reverse);
+        let rec = fetch_region(&index, &mut reader, &region).unwrap();
+        assert_eq!(&rec.id[..], b"chr1:2-7(-)");
+        assert_eq!(&rec.seq[..], b"CAAAC");
+
+        // windows line endings and the end of the record
+        let region = Region::new("chr2", 3, 6, Strand::Forward);
+        let rec = fetch_region(&index, &mut reader, &region).unwrap();
+        assert_eq!(&rec.seq[..], b"GAA");
+
+        let region = Region::new("chr2", 3, 3, Strand::Forward);
+        let rec = fetch_region(&index, &mut reader, &region).unwrap();
+        assert_eq!(&rec.seq[..], b"");
+    }
+
+    #[test]
+    fn test_extract_named() {
+        let fasta = b">a\nACGT\nAC\n>b desc\nGG\n>c\nTTTT\nTTTT\nT\n";
+        let index = FastaIndex::from_reader(&fasta[..]).unwrap();
+        let mut reader = Cursor::new(&fasta[..]);
+
+        let recs = extract_named(&index, &mut reader, &[b"c", b"a"]).unwrap();
+        assert_eq!(recs.len(), 2);
+        assert_eq!(&recs[0].id[..], b"c");
+        assert_eq!(&recs[0].seq[..], b"TTTTTTTTT");
+        assert_eq!(&recs[1].id[..], b"a");
+        assert_eq!(&recs[1].seq[..], b"ACGTAC");
+
+        let e = extract_named(&index, &mut reader, &[b"b", b"d"]).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::Invalid);
+        assert_eq!(e.context, "d");
+    }
+
+    #[test]
+    fn test_fetch_region_out_of_range() {
+        let index = FastaIndex::from_reader(FASTA).unwrap();
+        let mut reader = Cursor::new(FASTA);
+
+        let region = Region::new("chr1", 8, 11, Strand::Forward);
+        let e = fetch_region(&index, &mut reader, &region).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::Invalid);
+        assert_eq!(e.context, "chr1:8-11(+)");
+
+        let region = Region::new("chr1", 5, 4, Strand::Forward);
+        assert!(fetch_region(&index, &mut reader, &region).is_err());
+
+        let region = Region::new("chr3", 0, 1, Strand::Forward);
+        assert!(fetch_region(&index, &mut reader, &region).is_err());
+    }
+}
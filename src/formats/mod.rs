@@ -14,13 +14,26 @@
 mod buffer;
 mod fasta;
 mod fastq;
+mod headers;
+mod index;
+mod paired;
+mod reader;
+mod sort;
+mod split;
+mod writer;
 
-use std::cmp::min;
+use std::borrow::Cow;
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::fs;
 use std::fs::File;
-use std::io::{stdin, Cursor, Read};
-use std::path::Path;
+use std::io::{stdin, BufRead, Read};
+use std::path::{Path, PathBuf};
 use std::str;
 
+#[cfg(feature = "compression")]
+use std::io::Cursor;
+
 #[cfg(feature = "compression")]
 use bzip2::read::BzDecoder;
 #[cfg(feature = "compression")]
@@ -30,14 +43,212 @@ use xz2::read::XzDecoder;
 
 pub use crate::formats::buffer::{RecBuffer, RecParser};
 pub use crate::formats::fasta::{FastaParser, FastaRecord};
-pub use crate::formats::fastq::{FastqParser, FastqRecord};
+pub use crate::formats::fastq::{estimate_fastq_records, FastqParser, FastqRecord};
+pub use crate::formats::headers::{parse_headers, parse_ncbi_header};
+pub use crate::formats::index::{
+    extract_named, fetch_region, FastaIndex, FastaIndexEntry, Region, Strand,
+};
+pub use crate::formats::paired::{interleave, parse_paired};
+pub use crate::formats::reader::{batched, Batches, SequenceReader};
+pub use crate::formats::sort::{sort_records, sort_records_in_chunks, Order, SortKey};
+pub use crate::formats::split::split_by_hash;
+#[cfg(feature = "compression")]
+pub use crate::formats::writer::write_compressed;
+pub use crate::formats::writer::{
+    dedup_by_id, map_quality, parse_and_tee, SequenceFormat, SequenceWriter,
+};
+use crate::sequence::{Sequence, DNA_ALPHABET, PROTEIN_ALPHABET};
 use crate::sequence_record::SequenceRecord;
-use crate::util::{ParseError, ParseErrorType};
+use crate::util::{header_id, memchr_both, wildcard_match, ParseError, ParseErrorType};
 
 static BUF_SIZE: usize = 256 * 1024;
 
+/// How sequences' case should be changed while they're parsed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CaseMode {
+    /// Leave sequences exactly as they are in the file
+    #[default]
+    Preserve,
+    /// Uppercase every sequence
+    Upper,
+    /// Lowercase every sequence
+    Lower,
+}
+
+impl CaseMode {
+    /// Change the case of a sequence, in place if it's already been copied
+    /// out of the buffer (e.g. when stripping line endings out of it)
+    fn apply(self, seq: &mut Cow<[u8]>) {
+        match self {
+            CaseMode::Preserve => {}
+            CaseMode::Upper => {
+                if seq.iter().any(u8::is_ascii_lowercase) {
+                    seq.to_mut().make_ascii_uppercase();
+                }
+            }
+            CaseMode::Lower => {
+                if seq.iter().any(u8::is_ascii_uppercase) {
+                    seq.to_mut().make_ascii_lowercase();
+                }
+            }
+        }
+    }
+}
+
+/// What kind of sequences a file holds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoleculeType {
+    Nucleotide,
+    Protein,
+}
+
+impl MoleculeType {
+    /// The alphabet sequences of this type are made of, e.g. for
+    /// `ParseOptions::alphabet`
+    pub fn alphabet(self) -> &'static [u8] {
+        match self {
+            MoleculeType::Nucleotide => DNA_ALPHABET,
+            MoleculeType::Protein => PROTEIN_ALPHABET,
+        }
+    }
+}
+
+/// Options controlling how strictly FASTX files are parsed.
+///
+/// ```
+/// use needletail::formats::ParseOptions;
+///
+/// let options = ParseOptions::new().strict_fastq(true);
+/// assert!(options.strict_fastq);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParseOptions {
+    /// Check that every FASTQ record is exactly four lines (header,
+    /// sequence, `+` and quality) and error on the first one that isn't
+    /// (see `FastqRecord::check_strict`)
+    pub strict_fastq: bool,
+    /// Error on any record with a sequence character that's not in this
+    /// alphabet (e.g. `sequence::DNA_ALPHABET` or `sequence::PROTEIN_ALPHABET`)
+    pub alphabet: Option<&'static [u8]>,
+    /// Error if two records have the same id (everything in the header
+    /// before the first whitespace); this keeps every id in memory
+    pub unique_ids: bool,
+    /// Error if a record takes up more than this many bytes of the file
+    /// (including its header, quality and line endings); this also stops a
+    /// single huge record from growing the read buffer without bound
+    pub max_record_len: Option<usize>,
+    /// Compute a CRC32 of every record's raw bytes (stored in
+    /// `SequenceRecord::checksum`), e.g. for checking against a manifest
+    pub checksum: bool,
+    /// Parse the stream as FASTA-like records whose header lines start
+    /// with this character instead of `>` (e.g. `;`) rather than detecting
+    /// the file type
+    pub header_char: Option<u8>,
+    /// Leave FASTA sequences exactly as they are in the file (including
+    /// the line endings of wrapped sequences) instead of stripping them;
+    /// this also saves copying multi-line sequences
+    pub raw: bool,
+    /// Change the case of every sequence as it's parsed
+    pub case: CaseMode,
+    /// Treat a stream that doesn't start with `>` or `@` as a single bare
+    /// sequence (with the id `sequence`) instead of erroring; all of its
+    /// whitespace is stripped out and it's reported as FASTA
+    pub assume_raw_sequence: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    pub fn strict_fastq(mut self, strict: bool) -> Self {
+        self.strict_fastq = strict;
+        self
+    }
+
+    pub fn alphabet(mut self, alphabet: Option<&'static [u8]>) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    pub fn unique_ids(mut self, unique: bool) -> Self {
+        self.unique_ids = unique;
+        self
+    }
+
+    pub fn max_record_len(mut self, max_len: Option<usize>) -> Self {
+        self.max_record_len = max_len;
+        self
+    }
+
+    pub fn checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    pub fn header_char(mut self, header_char: Option<u8>) -> Self {
+        self.header_char = header_char;
+        self
+    }
+
+    pub fn raw(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    pub fn case(mut self, case: CaseMode) -> Self {
+        self.case = case;
+        self
+    }
+
+    pub fn assume_raw_sequence(mut self, assume_raw: bool) -> Self {
+        self.assume_raw_sequence = assume_raw;
+        self
+    }
+
+    /// Check a record against these options
+    fn check_record(&self, rec: &SequenceRecord) -> Result<(), ParseError> {
+        if let Some(alphabet) = self.alphabet {
+            if let Some(pos) = rec.seq.find_invalid(alphabet) {
+                return Err(ParseError::new(
+                    format!(
+                        "Sequence has a character not in the alphabet ('{}' at position {})",
+                        rec.seq[pos].escape_ascii(),
+                        pos
+                    ),
+                    ParseErrorType::InvalidRecord,
+                )
+                .context(String::from_utf8_lossy(&rec.id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A CRC32 of the raw bytes of a record (ignoring the line ending after it
+/// so the last record in a file checksums the same as the others)
+fn record_checksum(raw: &[u8]) -> u32 {
+    let end = raw
+        .iter()
+        .rposition(|c| *c != b'\n' && *c != b'\r')
+        .map_or(0, |i| i + 1);
+    crc32fast::hash(&raw[..end])
+}
+
+/// Error out of a parse when a record is taking up more than `max_len`
+/// bytes of the file
+fn check_record_len(len: usize, max_len: Option<usize>) -> Result<(), ParseError> {
+    match max_len {
+        Some(max_len) if len > max_len => Err(ParseError::new(
+            format!("Record is longer than the maximum of {} bytes", max_len),
+            ParseErrorType::RecordTooLarge,
+        )),
+        _ => Ok(()),
+    }
+}
+
 macro_rules! parse_stream {
-    ($reader:expr, $first:expr, $reader_type: ty, $rec: ident, $handler: block) => {{
+    ($reader:expr, $first:expr, $new_parser: expr, $max_len: expr, $rec: ident, $count: pat, $raw: pat, $handler: block) => {{
         use $crate::formats::{RecBuffer, RecParser};
         let mut buffer = RecBuffer::new($reader, $first)?;
         let mut record_count: usize = 0;
@@ -48,67 +259,449 @@ macro_rules! parse_stream {
         // if !buffer.refill(used).map_err(|e| e.record(record_count))? {
         loop {
             let used = {
-                let mut rec_reader = <$reader_type>::from_buffer(&buffer.buf, buffer.last);
-                for s in rec_reader.by_ref() {
+                let mut rec_reader = $new_parser(&buffer.buf, buffer.last);
+                let mut rec_start = 0;
+                while let Some(s) = rec_reader.next() {
                     record_count += 1;
                     let $rec = s.map_err(|e| e.record(record_count))?;
+                    let rec_end = rec_reader.used();
+                    check_record_len(rec_end - rec_start, $max_len)
+                        .map_err(|e| e.record(record_count))?;
+                    let $count = record_count;
+                    let $raw = &buffer.buf[rec_start..rec_end];
+                    rec_start = rec_end;
                     $handler
                 }
                 rec_reader.used()
             };
+            // if not even one record fit in the buffer, the next one is
+            // already too big to bother growing the buffer for
+            if used == 0 && !buffer.last {
+                check_record_len(buffer.buf.len(), $max_len)
+                    .map_err(|e| e.record(record_count + 1))?;
+            }
+            if buffer.refill(used).map_err(|e| e.record(record_count))? {
+                break;
+            }
+        }
+        let rec_reader = $new_parser(&buffer.buf, buffer.last);
+        rec_reader.eof().map_err(|e| e.record(record_count + 1))?;
+    }};
+}
+
+/// Like `parse_stream`, but instead of stopping at the first malformed record
+/// this passes the error to `$err_callback` and skips ahead to the next
+/// line starting with `$marker` (the next plausible start of a record).
+macro_rules! parse_stream_lenient {
+    ($reader:expr, $first:expr, $reader_type: ty, $marker: expr, $rec: ident, $err_callback: expr, $handler: block) => {{
+        use $crate::formats::{RecBuffer, RecParser};
+        let mut buffer = RecBuffer::new($reader, $first)?;
+        let mut record_count: usize = 0;
+        loop {
+            let (used, failed) = {
+                let mut rec_reader = <$reader_type>::from_buffer(&buffer.buf, buffer.last);
+                let mut failed = false;
+                for s in rec_reader.by_ref() {
+                    record_count += 1;
+                    match s {
+                        Ok($rec) => $handler,
+                        Err(e) => {
+                            $err_callback(e.record(record_count));
+                            failed = true;
+                            break;
+                        }
+                    }
+                }
+                (rec_reader.used(), failed)
+            };
+            let used = if failed {
+                // the bad record starts at `used` so look for the next record
+                // after it; if there isn't one in the buffer, throw away all
+                // but the last byte (which may be the `\n` before a record)
+                match memchr_both(b'\n', $marker, &buffer.buf[used..]) {
+                    Some(i) => used + i + 1,
+                    None if buffer.last => buffer.buf.len(),
+                    None => max(used + 1, buffer.buf.len() - 1),
+                }
+            } else {
+                used
+            };
             if buffer.refill(used).map_err(|e| e.record(record_count))? {
                 break;
             }
         }
         let rec_reader = <$reader_type>::from_buffer(&buffer.buf, buffer.last);
+        if let Err(e) = rec_reader.eof() {
+            $err_callback(e.record(record_count + 1));
+        }
+    }};
+}
+
+/// Like `parse_stream`, but parses records directly out of the internal
+/// buffer of a `BufRead` instead of copying everything into a `RecBuffer`.
+/// Only a record that straddles the end of the reader's buffer gets copied
+/// (into `spill`) and once that record's been parsed we go back to reading
+/// out of the reader's buffer.
+macro_rules! parse_bufread {
+    ($reader:expr, $reader_type: ty, $rec: ident, $handler: block) => {{
+        use $crate::formats::RecParser;
+        let reader = $reader;
+        let mut record_count: usize = 0;
+        let mut spill: Vec<u8> = Vec::new();
+        loop {
+            let data = reader
+                .fill_buf()
+                .map_err(|e| ParseError::from(e).record(record_count))?;
+            let last = data.is_empty();
+            if spill.is_empty() {
+                let used = {
+                    let mut rec_reader = <$reader_type>::from_buffer(data, last);
+                    for s in rec_reader.by_ref() {
+                        record_count += 1;
+                        let $rec = s.map_err(|e| e.record(record_count))?;
+                        $handler
+                    }
+                    rec_reader.used()
+                };
+                if last {
+                    break;
+                }
+                let len = data.len();
+                if used < len {
+                    // the rest of the buffer is an incomplete record
+                    spill.extend_from_slice(&data[used..]);
+                }
+                reader.consume(len);
+            } else {
+                // add on just enough data to (hopefully) finish the record
+                let prev = spill.len();
+                let amt = min(data.len(), max(prev, 1024));
+                spill.extend_from_slice(&data[..amt]);
+                let used = {
+                    let mut rec_reader = <$reader_type>::from_buffer(&spill, last);
+                    for s in rec_reader.by_ref() {
+                        record_count += 1;
+                        let $rec = s.map_err(|e| e.record(record_count))?;
+                        $handler
+                    }
+                    rec_reader.used()
+                };
+                if last {
+                    spill.drain(..used);
+                    break;
+                }
+                if used >= prev {
+                    // the straddling record is done so anything past it can be
+                    // parsed out of the reader's buffer again
+                    reader.consume(used - prev);
+                    spill.clear();
+                } else {
+                    spill.drain(..used);
+                    reader.consume(amt);
+                }
+            }
+        }
+        let rec_reader = <$reader_type>::from_buffer(&spill, true);
         rec_reader.eof().map_err(|e| e.record(record_count + 1))?;
     }};
 }
 
+/// Check that a record's id hasn't been seen before (`seen_ids` maps ids to
+/// the record number they were first seen at)
+fn check_duplicate_id(
+    seen_ids: &mut HashMap<Vec<u8>, usize>,
+    header: &[u8],
+    record_count: usize,
+) -> Result<(), ParseError> {
+    let id = header_id(header);
+    if let Some(first) = seen_ids.get(id) {
+        return Err(ParseError::new(
+            format!(
+                "Record {} has the same id as record {}",
+                record_count, first
+            ),
+            ParseErrorType::DuplicateId,
+        )
+        .record(record_count)
+        .context(String::from_utf8_lossy(id)));
+    }
+    seen_ids.insert(id.to_vec(), record_count);
+    Ok(())
+}
+
+/// Infer the type of the sequencing data from the start of the file
+fn detect_file_type(start_data: &[u8]) -> Result<&'static str, ParseError> {
+    match start_data.first() {
+        Some(b'>') => Ok("FASTA"),
+        Some(b'@') => Ok("FASTQ"),
+        _ => {
+            let context = String::from_utf8_lossy(&start_data[..min(32, start_data.len())]);
+            Err(
+                ParseError::new("Could not detect file type", ParseErrorType::InvalidHeader)
+                    .record(0)
+                    .context(context),
+            )
+        }
+    }
+}
+
+/// Read the rest of a stream to check if there's anything in it besides
+/// whitespace (in which case there's nothing to parse). Everything that's
+/// read is added on to `start_data` so it can still be parsed.
+fn is_blank<R: Read + ?Sized>(
+    reader: &mut R,
+    start_data: &mut Vec<u8>,
+) -> Result<bool, ParseError> {
+    let mut buf = vec![0; 8 * 1024];
+    loop {
+        let amt_read = reader.read(&mut buf)?;
+        if amt_read == 0 {
+            return Ok(true);
+        }
+        start_data.extend_from_slice(&buf[..amt_read]);
+        if !buf[..amt_read].iter().all(u8::is_ascii_whitespace) {
+            return Ok(false);
+        }
+    }
+}
+
+/// The UTF-8 byte order mark some (Windows) tools put at the start of files
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Read the first chunk of a stream; this will be at least two bytes long
+/// (enough to check for compression) unless the stream is shorter than that.
+/// A UTF-8 byte order mark at the start of the stream is skipped over.
+fn read_start<R: Read + ?Sized>(reader: &mut R) -> Result<Vec<u8>, ParseError> {
+    let mut first = vec![0; BUF_SIZE];
+    let mut amt_read = 0;
+    // read enough to see past a byte order mark
+    while amt_read < UTF8_BOM.len() + 2 {
+        match reader.read(&mut first[amt_read..])? {
+            0 => break,
+            n => amt_read += n,
+        }
+    }
+    first.truncate(amt_read);
+    if first.starts_with(UTF8_BOM) {
+        first.drain(..UTF8_BOM.len());
+    }
+    Ok(first)
+}
+
+/// Throw away everything before the first line that starts with `>` (e.g.
+/// stray sequence left at the start of a file by a bad concatenation) and
+/// return what's left of the data read so far with how many bytes were
+/// skipped. If there's no header at all, everything is skipped.
+fn skip_to_first_header<R: Read + ?Sized>(
+    reader: &mut R,
+    mut data: Vec<u8>,
+) -> Result<(Vec<u8>, usize), ParseError> {
+    let mut skipped = 0;
+    loop {
+        if data.first() == Some(&b'>') {
+            return Ok((data, skipped));
+        }
+        // keep the last byte in case it's the `\n` before a header
+        let used = match memchr_both(b'\n', b'>', &data) {
+            Some(i) => i + 1,
+            None => data.len().saturating_sub(1),
+        };
+        data.drain(..used);
+        skipped += used;
+
+        let mut buf = vec![0; BUF_SIZE];
+        let amt_read = reader.read(&mut buf)?;
+        if amt_read == 0 {
+            if data.first() != Some(&b'>') {
+                skipped += data.len();
+                data.clear();
+            }
+            return Ok((data, skipped));
+        }
+        data.extend_from_slice(&buf[..amt_read]);
+    }
+}
+
 /// Internal function abstracting over byte and file FASTX parsing
 #[inline]
 fn seq_reader<F, R, T>(
     reader: &mut R,
     mut callback: F,
     type_callback: &mut T,
-    start_data: Vec<u8>,
+    mut start_data: Vec<u8>,
+    options: &ParseOptions,
 ) -> Result<(), ParseError>
 where
     F: for<'a> FnMut(SequenceRecord<'a>) -> (),
     R: Read,
     T: ?Sized + FnMut(&'static str) -> (),
 {
-    // infer the type of the sequencing data
-    let file_type = match start_data[0] {
-        b'>' => Ok("FASTA"),
-        b'@' => Ok("FASTQ"),
-        _ => {
-            let context = String::from_utf8_lossy(&start_data[..min(32, start_data.len())]);
-            Err(
-                ParseError::new("Could not detect file type", ParseErrorType::InvalidHeader)
-                    .record(0)
-                    .context(context),
-            )
+    // an empty (or all whitespace) stream has no records in it
+    if start_data.iter().all(u8::is_ascii_whitespace) && is_blank(reader, &mut start_data)? {
+        return Ok(());
+    }
+    if options.assume_raw_sequence
+        && options.header_char.is_none()
+        && !matches!(start_data.first(), Some(b'>') | Some(b'@'))
+    {
+        return parse_raw_sequence(reader, callback, type_callback, start_data, options);
+    }
+    let file_type = match options.header_char {
+        Some(header_char) => {
+            if start_data[0] != header_char {
+                let context = String::from_utf8_lossy(&start_data[..min(64, start_data.len())]);
+                return Err(ParseError::new(
+                    format!("Record must start with '{}'", header_char.escape_ascii()),
+                    ParseErrorType::InvalidHeader,
+                )
+                .context(context));
+            }
+            "FASTA"
         }
-    }?;
+        None => detect_file_type(&start_data)?,
+    };
     type_callback(file_type);
+    let header_char = options.header_char.unwrap_or(b'>');
 
+    let mut seen_ids = HashMap::new();
     match file_type {
-        "FASTA" => parse_stream!(reader, start_data, FastaParser, rec, {
-            callback(SequenceRecord::from(rec))
-        }),
-        "FASTQ" => parse_stream!(reader, start_data, FastqParser, rec, {
-            callback(SequenceRecord::from(rec))
-        }),
+        "FASTA" => parse_stream!(
+            reader,
+            start_data,
+            |buf, last| FastaParser::from_buffer(buf, last).header_char(header_char),
+            options.max_record_len,
+            rec,
+            record_count,
+            raw,
+            {
+                let mut rec = if options.raw {
+                    SequenceRecord::new(rec.id.into(), rec.seq.into(), None)
+                } else {
+                    SequenceRecord::from(rec)
+                };
+                options.case.apply(&mut rec.seq);
+                if options.checksum {
+                    rec.checksum = Some(record_checksum(raw));
+                }
+                options
+                    .check_record(&rec)
+                    .map_err(|e| e.record(record_count))?;
+                if options.unique_ids {
+                    check_duplicate_id(&mut seen_ids, &rec.id, record_count)?;
+                }
+                callback(rec)
+            }
+        ),
+        "FASTQ" => parse_stream!(
+            reader,
+            start_data,
+            FastqParser::from_buffer,
+            options.max_record_len,
+            rec,
+            record_count,
+            raw,
+            {
+                if options.strict_fastq {
+                    rec.check_strict().map_err(|e| e.record(record_count))?;
+                }
+                let mut rec = SequenceRecord::from(rec);
+                options.case.apply(&mut rec.seq);
+                if options.checksum {
+                    rec.checksum = Some(record_checksum(raw));
+                }
+                options
+                    .check_record(&rec)
+                    .map_err(|e| e.record(record_count))?;
+                if options.unique_ids {
+                    check_duplicate_id(&mut seen_ids, &rec.id, record_count)?;
+                }
+                callback(rec)
+            }
+        ),
         _ => panic!("A file type was inferred that could not be parsed"),
     };
     Ok(())
 }
 
+/// Read a whole stream in as one bare sequence (see
+/// `ParseOptions::assume_raw_sequence`)
+fn parse_raw_sequence<F, R, T>(
+    reader: &mut R,
+    mut callback: F,
+    type_callback: &mut T,
+    mut data: Vec<u8>,
+    options: &ParseOptions,
+) -> Result<(), ParseError>
+where
+    F: for<'a> FnMut(SequenceRecord<'a>),
+    R: Read,
+    T: ?Sized + FnMut(&'static str),
+{
+    reader.read_to_end(&mut data)?;
+    check_record_len(data.len(), options.max_record_len).map_err(|e| e.record(1))?;
+    type_callback("FASTA");
+    let mut rec = SequenceRecord::new(Cow::from(&b"sequence"[..]), data.strip_whitespace(), None);
+    options.case.apply(&mut rec.seq);
+    if options.checksum {
+        rec.checksum = Some(record_checksum(&data));
+    }
+    options.check_record(&rec).map_err(|e| e.record(1))?;
+    callback(rec);
+    Ok(())
+}
+
+/// Check if the start of a stream looks like it's compressed (with one of
+/// the formats `decompress` handles)
+fn is_compressed(first: &[u8]) -> bool {
+    cfg!(feature = "compression")
+        && (first.starts_with(&[0x1F, 0x8B])
+            || first.starts_with(&[0x42, 0x5A])
+            || first.starts_with(&[0xFD, 0x37]))
+}
+
+/// Figure out if a stream is compressed and wrap it in a decompressor if so.
+/// Returns the (decompressed) stream and the first chunk of data read out of
+/// it.
+#[cfg(feature = "compression")]
+fn decompress<'r, R: Read + 'r>(
+    mut reader: R,
+) -> Result<(Box<dyn Read + 'r>, Vec<u8>), ParseError> {
+    let first = read_start(&mut reader)?;
+
+    if first.starts_with(&[0x1F, 0x8B]) {
+        // gz files
+        let cursor = Cursor::new(first);
+        let mut gz_reader = MultiGzDecoder::new(cursor.chain(reader));
+        let data = read_start(&mut gz_reader)?;
+        Ok((Box::new(gz_reader), data))
+    } else if first.starts_with(&[0x42, 0x5A]) {
+        // bz files
+        let cursor = Cursor::new(first);
+        let mut bz_reader = BzDecoder::new(cursor.chain(reader));
+        let data = read_start(&mut bz_reader)?;
+        Ok((Box::new(bz_reader), data))
+    } else if first.starts_with(&[0xFD, 0x37]) {
+        // xz files
+        let cursor = Cursor::new(first);
+        let mut xz_reader = XzDecoder::new(cursor.chain(reader));
+        let data = read_start(&mut xz_reader)?;
+        Ok((Box::new(xz_reader), data))
+    } else {
+        Ok((Box::new(reader), first))
+    }
+}
+
 #[cfg(not(feature = "compression"))]
-pub fn parse_sequence_reader<F, R, T>(
+fn decompress<'r, R: Read + 'r>(
     mut reader: R,
-    mut type_callback: T,
+) -> Result<(Box<dyn Read + 'r>, Vec<u8>), ParseError> {
+    let first = read_start(&mut reader)?;
+    Ok((Box::new(reader), first))
+}
+
+pub fn parse_sequence_reader<F, R, T>(
+    reader: R,
+    type_callback: T,
     callback: F,
 ) -> Result<(), ParseError>
 where
@@ -118,70 +711,145 @@ where
 {
     //! Opens a `Read` stream and parses the FASTX records out. Also takes a "type_callback"
     //! that gets called as soon as we determine if the records are FASTA or FASTQ.
-    let mut first = vec![0, 0];
-    reader.read_exact(&mut first)?;
-    seq_reader(&mut reader, callback, &mut type_callback, &first)
+    //! If a file starts with a gzip or other header, transparently decompress it
+    //! (if needletail was built with the `compression` feature).
+    //! An empty stream (or one that's only whitespace) has no records, so neither
+    //! callback is called and this returns `Ok`.
+    parse_sequence_reader_with_options(reader, &ParseOptions::default(), type_callback, callback)
 }
 
-#[cfg(feature = "compression")]
-pub fn parse_sequence_reader<F, R, T>(
-    mut reader: R,
+/// Like `parse_sequence_reader`, but with `options` controlling how the
+/// records are parsed.
+pub fn parse_sequence_reader_with_options<F, R, T>(
+    reader: R,
+    options: &ParseOptions,
     mut type_callback: T,
     callback: F,
 ) -> Result<(), ParseError>
 where
-    F: for<'a> FnMut(SequenceRecord<'a>) -> (),
+    F: for<'a> FnMut(SequenceRecord<'a>),
     R: Read,
-    T: FnMut(&'static str) -> (),
+    T: FnMut(&'static str),
 {
-    //! Opens a `Read` stream and parses the FASTX records out. Also takes a "type_callback"
-    //! that gets called as soon as we determine if the records are FASTA or FASTQ.
-    //! If a file starts with a gzip or other header, transparently decompress it.
-    let mut first = vec![0; BUF_SIZE];
-    let amt_read = reader.read(&mut first)?;
-    if amt_read < 2 {
-        return Err(ParseError::new(
-            "File was too short",
-            ParseErrorType::Invalid,
-        ));
+    let (mut reader, first) = decompress(reader)?;
+    seq_reader(&mut reader, callback, &mut type_callback, first, options)
+}
+
+/// Like `parse_sequence_reader`, but for making a best effort at parsing
+/// messy files: instead of stopping at the first malformed record, the error
+/// is passed to `err_callback` and parsing resumes at the next line that
+/// looks like the start of a record (i.e. starts with `>` or `@`). Errors
+/// reading the stream are still returned.
+///
+/// A stream that doesn't start with a record has everything before its first
+/// `>` header skipped and parsed as FASTA (this can recover a file with stray
+/// sequence at the start from a bad concatenation); an `InvalidHeader` error
+/// saying how many bytes were skipped is passed to `err_callback`.
+///
+/// Note that because `@` can also start a line of FASTQ quality scores, a
+/// bad FASTQ record may produce several errors before parsing gets back on
+/// track.
+pub fn parse_sequence_reader_lenient<F, E, R, T>(
+    reader: R,
+    mut type_callback: T,
+    mut err_callback: E,
+    mut callback: F,
+) -> Result<(), ParseError>
+where
+    F: for<'a> FnMut(SequenceRecord<'a>),
+    E: FnMut(ParseError),
+    R: Read,
+    T: FnMut(&'static str),
+{
+    let (mut reader, mut start_data) = decompress(reader)?;
+    if start_data.iter().all(u8::is_ascii_whitespace) && is_blank(&mut reader, &mut start_data)? {
+        return Ok(());
     }
-    unsafe {
-        first.set_len(amt_read);
+    if !matches!(start_data.first(), Some(b'>') | Some(b'@')) {
+        let (data, skipped) = skip_to_first_header(&mut reader, start_data)?;
+        err_callback(
+            ParseError::new(
+                format!("Skipped {} bytes before the first record", skipped),
+                ParseErrorType::InvalidHeader,
+            )
+            .record(0),
+        );
+        if data.is_empty() {
+            return Ok(());
+        }
+        start_data = data;
     }
+    let file_type = detect_file_type(&start_data)?;
+    type_callback(file_type);
 
-    if first[0] == 0x1F && first[1] == 0x8B {
-        // gz files
-        let cursor = Cursor::new(first);
-        let mut gz_reader = MultiGzDecoder::new(cursor.chain(reader));
-        let mut data = vec![0; BUF_SIZE];
-        let amt_read = gz_reader.read(&mut data)?;
-        unsafe {
-            data.set_len(amt_read);
-        }
-        seq_reader(&mut gz_reader, callback, &mut type_callback, data)
-    } else if first[0] == 0x42 && first[1] == 0x5A {
-        // bz files
-        let cursor = Cursor::new(first);
-        let mut bz_reader = BzDecoder::new(cursor.chain(reader));
-        let mut data = vec![0; BUF_SIZE];
-        let amt_read = bz_reader.read(&mut data)?;
-        unsafe {
-            data.set_len(amt_read);
-        }
-        seq_reader(&mut bz_reader, callback, &mut type_callback, data)
-    } else if first[0] == 0xFD && first[1] == 0x37 {
-        // xz files
-        let cursor = Cursor::new(first);
-        let mut xz_reader = XzDecoder::new(cursor.chain(reader));
-        let mut data = vec![0; BUF_SIZE];
-        let amt_read = xz_reader.read(&mut data)?;
-        unsafe {
-            data.set_len(amt_read);
+    match file_type {
+        "FASTA" => parse_stream_lenient!(
+            &mut reader,
+            start_data,
+            FastaParser,
+            b'>',
+            rec,
+            err_callback,
+            { callback(SequenceRecord::from(rec)) }
+        ),
+        "FASTQ" => parse_stream_lenient!(
+            &mut reader,
+            start_data,
+            FastqParser,
+            b'@',
+            rec,
+            err_callback,
+            { callback(SequenceRecord::from(rec)) }
+        ),
+        _ => panic!("A file type was inferred that could not be parsed"),
+    };
+    Ok(())
+}
+
+/// Parses the FASTX records out of a `BufRead`, reading them directly out
+/// of its buffer instead of copying them into one of our own (so this is
+/// faster than `parse_sequence_reader` if you already have e.g. a
+/// `BufReader`). Like `parse_sequence_reader`, this takes a "type_callback"
+/// that gets called as soon as we determine if the records are FASTA or FASTQ.
+///
+/// The data is not automatically decompressed. An empty stream (or one that's
+/// only whitespace) has no records, so neither callback is called and this
+/// returns `Ok`.
+pub fn parse_sequence_bufread<F, R, T>(
+    mut reader: R,
+    mut type_callback: T,
+    mut callback: F,
+) -> Result<(), ParseError>
+where
+    F: for<'a> FnMut(SequenceRecord<'a>),
+    R: BufRead,
+    T: FnMut(&'static str),
+{
+    if reader.fill_buf()?.starts_with(UTF8_BOM) {
+        reader.consume(UTF8_BOM.len());
+    }
+    let start_data = reader.fill_buf()?;
+    if start_data.iter().all(u8::is_ascii_whitespace) {
+        // an empty (or all whitespace) stream has no records in it
+        let mut start_data = start_data.to_vec();
+        if is_blank(&mut reader, &mut start_data)? {
+            return Ok(());
         }
-        seq_reader(&mut xz_reader, callback, &mut type_callback, data)
-    } else {
-        seq_reader(&mut reader, callback, &mut type_callback, first)
+        detect_file_type(&start_data)?;
     }
+    let file_type = detect_file_type(reader.fill_buf()?)?;
+    type_callback(file_type);
+
+    match file_type {
+        "FASTA" => parse_bufread!(&mut reader, FastaParser, rec, {
+            callback(SequenceRecord::from(rec))
+        }),
+        "FASTQ" => parse_bufread!(&mut reader, FastqParser, rec, {
+            callback(SequenceRecord::from(rec))
+        }),
+        _ => panic!("A file type was inferred that could not be parsed"),
+    };
+    Ok(())
 }
 
 /// This is a convenience method for easy drop into CLI programs. It will
@@ -207,3 +875,1016 @@ where
         parse_sequence_reader(File::open(&path)?, type_callback, callback)
     }
 }
+
+/// What `parse_sequence_path_with_info` found out about a file
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputInfo {
+    /// `"FASTA"` or `"FASTQ"` (or `None` for an empty file)
+    pub file_type: Option<&'static str>,
+    /// A guess at what's in the file from its extension (see
+    /// `molecule_type_hint`)
+    pub molecule_type: Option<MoleculeType>,
+}
+
+/// Like `parse_sequence_path`, but instead of a "type_callback" this returns
+/// what kind of file it was, along with a hint of whether it holds
+/// nucleotides or protein from its extension (see `molecule_type_hint`).
+///
+/// ```no_run
+/// use needletail::formats::{parse_sequence_path_with_info, MoleculeType};
+///
+/// let info = parse_sequence_path_with_info("proteins.faa", |_| {}).unwrap();
+/// if info.molecule_type == Some(MoleculeType::Protein) {
+///     // ...
+/// }
+/// ```
+pub fn parse_sequence_path_with_info<F, P>(path: P, callback: F) -> Result<InputInfo, ParseError>
+where
+    F: for<'a> FnMut(SequenceRecord<'a>),
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let mut info = InputInfo {
+        file_type: None,
+        molecule_type: molecule_type_hint(path),
+    };
+    parse_sequence_path(path, |file_type| info.file_type = Some(file_type), callback)?;
+    Ok(info)
+}
+
+/// Guess what kind of sequences a FASTA file holds from NCBI's extensions
+/// for them: `.fna` (nucleotides), `.ffn` (genes), `.frn` (non-coding RNA)
+/// and `.faa` (amino acids), ignoring a compression extension after them
+/// (e.g. `.faa.gz`). Returns `None` for any other extension; the file's
+/// contents aren't looked at.
+///
+/// ```
+/// use needletail::formats::{molecule_type_hint, MoleculeType, ParseOptions};
+///
+/// let hint = molecule_type_hint("GCF_000005845.2_protein.faa.gz");
+/// assert_eq!(hint, Some(MoleculeType::Protein));
+/// let options = ParseOptions::new().alphabet(hint.map(|m| m.alphabet()));
+/// ```
+pub fn molecule_type_hint<P: AsRef<Path>>(path: P) -> Option<MoleculeType> {
+    let mut path = path.as_ref();
+    let stem;
+    if let Some("gz") | Some("bz2") | Some("xz") = path.extension().and_then(|e| e.to_str()) {
+        stem = path.with_extension("");
+        path = &stem;
+    }
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match &ext[..] {
+        "fna" | "ffn" | "frn" => Some(MoleculeType::Nucleotide),
+        "faa" => Some(MoleculeType::Protein),
+        _ => None,
+    }
+}
+
+/// Find the files matching a wildcard pattern (in sorted order). Only the
+/// file name part of the pattern can have wildcards.
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, ParseError> {
+    let invalid = |msg: &str| {
+        ParseError::new(msg, ParseErrorType::Invalid).context(pattern.to_string_lossy())
+    };
+    let file_pattern = match pattern.file_name() {
+        Some(p) => p.to_string_lossy(),
+        None => return Err(invalid("Pattern doesn't have a file name")),
+    };
+    let dir = match pattern.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    if dir.to_string_lossy().contains(&['*', '?'][..]) {
+        return Err(invalid("Wildcards are only supported in file names"));
+    }
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if wildcard_match(file_pattern.as_bytes(), name.to_string_lossy().as_bytes()) {
+            paths.push(entry.path());
+        }
+    }
+    if paths.is_empty() {
+        return Err(invalid("No files matched the pattern"));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Parse all of the files matching `pattern` (sorted by name) as if they
+/// were one file, e.g. for data sharded into `sample.part000.fa.gz`,
+/// `sample.part001.fa.gz`, etc. Only the file name part of the pattern can
+/// have wildcards: `*` matches any run of characters and `?` matches any
+/// single character.
+///
+/// Each file is decompressed separately and `type_callback` is called for
+/// each file. Record numbers in errors count from the start of the first file.
+/// Returns each file that was parsed with how many records were in it.
+pub fn parse_sequence_glob<F, P, T>(
+    pattern: P,
+    mut type_callback: T,
+    mut callback: F,
+) -> Result<Vec<(PathBuf, usize)>, ParseError>
+where
+    F: for<'a> FnMut(SequenceRecord<'a>),
+    P: AsRef<Path>,
+    T: FnMut(&'static str),
+{
+    let mut record_count = 0;
+    let mut counts = Vec::new();
+    for path in expand_glob(pattern.as_ref())? {
+        let prev_count = record_count;
+        parse_sequence_reader(File::open(&path)?, &mut type_callback, |rec| {
+            record_count += 1;
+            callback(rec)
+        })
+        .map_err(|e| {
+            let record = e.record;
+            e.record(prev_count + record)
+        })?;
+        counts.push((path, record_count - prev_count));
+    }
+    Ok(counts)
+}
+
+/// Parse a series of streams one after another as if they were one stream.
+/// Every stream is decompressed and has its format detected on its own (so
+/// e.g. a gzipped FASTA file can be followed by an uncompressed FASTQ file)
+/// and `type_callback` is called for each of them. Record numbers in errors
+/// count from the start of the first stream. Returns how many records were
+/// in each stream.
+pub fn parse_many<I, F, T>(
+    readers: I,
+    mut type_callback: T,
+    mut callback: F,
+) -> Result<Vec<usize>, ParseError>
+where
+    I: IntoIterator,
+    I::Item: Read,
+    F: for<'a> FnMut(SequenceRecord<'a>),
+    T: FnMut(&'static str),
+{
+    let mut record_count = 0;
+    let mut counts = Vec::new();
+    for reader in readers {
+        let prev_count = record_count;
+        parse_sequence_reader(reader, &mut type_callback, |rec| {
+            record_count += 1;
+            callback(rec)
+        })
+        .map_err(|e| {
+            let record = e.record;
+            e.record(prev_count + record)
+        })?;
+        counts.push(record_count - prev_count);
+    }
+    Ok(counts)
+}
+
+/// A pattern for picking out records by id (everything in the header
+/// before the first whitespace) in `parse_matching`
+#[derive(Clone, Debug)]
+pub enum IdPattern {
+    /// The id contains this
+    Substring(Vec<u8>),
+    /// The id matches this shell-style wildcard pattern (where `*` matches
+    /// any run of characters and `?` any single one)
+    Glob(Vec<u8>),
+    /// The id matches this regular expression (anywhere, unless it's
+    /// anchored)
+    #[cfg(feature = "regex")]
+    Regex(regex::bytes::Regex),
+}
+
+impl IdPattern {
+    /// Check if a record's header matches the pattern
+    pub fn matches(&self, header: &[u8]) -> bool {
+        let id = header_id(header);
+        match self {
+            IdPattern::Substring(s) => s.is_empty() || id.windows(s.len()).any(|w| w == &s[..]),
+            IdPattern::Glob(pattern) => wildcard_match(pattern, id),
+            #[cfg(feature = "regex")]
+            IdPattern::Regex(re) => re.is_match(id),
+        }
+    }
+}
+
+/// Parse a stream, only calling `callback` on the records with an id that
+/// matches `pattern`. The ids are checked before anything else is done with
+/// the records, so skipping a record is cheap (e.g. its sequence isn't
+/// copied to strip out line endings).
+///
+/// ```
+/// use needletail::formats::{parse_matching, IdPattern};
+///
+/// let data = b">chr1\nACGT\n>scaffold_1\nGG\n>chrX\nTT\n";
+/// let mut ids = Vec::new();
+/// let pattern = IdPattern::Glob(b"chr*".to_vec());
+/// parse_matching(&data[..], &pattern, |rec| ids.push(rec.id.to_vec())).unwrap();
+/// assert_eq!(ids, vec![b"chr1".to_vec(), b"chrX".to_vec()]);
+/// ```
+pub fn parse_matching<F, R>(
+    reader: R,
+    pattern: &IdPattern,
+    mut callback: F,
+) -> Result<(), ParseError>
+where
+    F: for<'a> FnMut(SequenceRecord<'a>),
+    R: Read,
+{
+    let (mut reader, mut start_data) = decompress(reader)?;
+    if start_data.iter().all(u8::is_ascii_whitespace) && is_blank(&mut reader, &mut start_data)? {
+        return Ok(());
+    }
+    match detect_file_type(&start_data)? {
+        "FASTA" => parse_stream!(
+            &mut reader,
+            start_data,
+            FastaParser::from_buffer,
+            None,
+            rec,
+            _,
+            _,
+            {
+                if pattern.matches(rec.id) {
+                    callback(SequenceRecord::from(rec))
+                }
+            }
+        ),
+        "FASTQ" => parse_stream!(
+            &mut reader,
+            start_data,
+            FastqParser::from_buffer,
+            None,
+            rec,
+            _,
+            _,
+            {
+                if pattern.matches(rec.id) {
+                    callback(SequenceRecord::from(rec))
+                }
+            }
+        ),
+        _ => panic!("A file type was inferred that could not be parsed"),
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::io::{self, BufReader, Cursor, Read};
+
+    use super::{parse_sequence_bufread, parse_sequence_reader, parse_sequence_reader_lenient};
+    use crate::util::ParseErrorType;
+
+    /// A reader that only gives back a few bytes at a time
+    struct ShortReads<'a>(&'a [u8], usize);
+
+    impl<'a> Read for ShortReads<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.1).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    type Rec = (Vec<u8>, Vec<u8>, Option<Vec<u8>>);
+
+    fn read_records(path: &str) -> Vec<Rec> {
+        let mut recs = Vec::new();
+        parse_sequence_reader(
+            File::open(path).unwrap(),
+            |_| {},
+            |rec| {
+                recs.push((
+                    rec.id.to_vec(),
+                    rec.seq.to_vec(),
+                    rec.qual.map(|q| q.to_vec()),
+                ))
+            },
+        )
+        .unwrap();
+        recs
+    }
+
+    #[test]
+    fn test_bufread_matches_reader() {
+        for path in &["./tests/data/28S.fasta", "./tests/data/PRJNA271013_head.fq"] {
+            let expected = read_records(path);
+            // small capacities force records to straddle the reader's buffer
+            for capacity in &[7, 1000, 8 * 1024, 1024 * 1024] {
+                let reader = BufReader::with_capacity(*capacity, File::open(path).unwrap());
+                let mut recs = Vec::new();
+                parse_sequence_bufread(
+                    reader,
+                    |_| {},
+                    |rec| {
+                        recs.push((
+                            rec.id.to_vec(),
+                            rec.seq.to_vec(),
+                            rec.qual.map(|q| q.to_vec()),
+                        ))
+                    },
+                )
+                .unwrap();
+                assert_eq!(recs, expected, "{} with capacity {}", path, capacity);
+            }
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_gzip_round_trip() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::{Read, Write};
+
+        let path = "./tests/data/PRJNA271013_head.fq";
+        let mut data = Vec::new();
+        File::open(path).unwrap().read_to_end(&mut data).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let gz_data = encoder.finish().unwrap();
+
+        let mut recs = Vec::new();
+        parse_sequence_reader(
+            Cursor::new(gz_data),
+            |_| {},
+            |rec| {
+                recs.push((
+                    rec.id.to_vec(),
+                    rec.seq.to_vec(),
+                    rec.qual.map(|q| q.to_vec()),
+                ))
+            },
+        )
+        .unwrap();
+        assert_eq!(recs, read_records(path));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_gzip_empty_first_member() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let gzip = |data: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        };
+        // a couple of empty members and then the real data in two more
+        let mut gz_data = gzip(b"");
+        gz_data.extend(gzip(b""));
+        gz_data.extend(gzip(b">a\nACGT\n"));
+        gz_data.extend(gzip(b">b\nGG\n"));
+
+        let mut ids = Vec::new();
+        let res = parse_sequence_reader(
+            Cursor::new(gz_data),
+            |file_type| assert_eq!(file_type, "FASTA"),
+            |rec| ids.push(rec.id.to_vec()),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(ids, vec![b"a".to_vec(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn test_lenient() {
+        let data = b"@A\nACGT\n+\nIIII\n@B\nACGT\n+\nII\n@C\nGG\n+\nII\n";
+        let e = parse_sequence_reader(Cursor::new(&data[..]), |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.record, 2);
+
+        let mut ids = Vec::new();
+        let mut errors = Vec::new();
+        let res = parse_sequence_reader_lenient(
+            Cursor::new(&data[..]),
+            |_| {},
+            |e| errors.push(e),
+            |rec| ids.push(rec.id.to_vec()),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(ids, vec![b"A".to_vec(), b"C".to_vec()]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error_type, ParseErrorType::InvalidRecord);
+        assert_eq!(errors[0].record, 2);
+
+        // a FASTA record with no sequence at all is fine
+        let data = b">A\nACGT\n>B\n>C\nGG\n";
+        let mut ids = Vec::new();
+        let mut errors = Vec::new();
+        let res = parse_sequence_reader_lenient(
+            Cursor::new(&data[..]),
+            |_| {},
+            |e| errors.push(e),
+            |rec| ids.push(rec.id.to_vec()),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(ids, vec![b"A".to_vec(), b"B".to_vec(), b"C".to_vec()]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_lenient_leading_junk() {
+        use super::BUF_SIZE;
+
+        let data = b"GATTACA\nAC>GT\n>A\nACGT\n>B\nGG\n";
+        assert!(parse_sequence_reader(Cursor::new(&data[..]), |_| {}, |_| {}).is_err());
+
+        let mut ids = Vec::new();
+        let mut errors = Vec::new();
+        let res = parse_sequence_reader_lenient(
+            Cursor::new(&data[..]),
+            |t| assert_eq!(t, "FASTA"),
+            |e| errors.push(e),
+            |rec| ids.push(rec.id.to_vec()),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(ids, vec![b"A".to_vec(), b"B".to_vec()]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error_type, ParseErrorType::InvalidHeader);
+        assert_eq!(errors[0].msg, "Skipped 14 bytes before the first record");
+
+        // junk that's bigger than the first read of the stream
+        let mut data = vec![b'N'; BUF_SIZE + 10];
+        data.extend_from_slice(b"\n>A\nACGT\n");
+        let mut ids = Vec::new();
+        let mut errors = Vec::new();
+        parse_sequence_reader_lenient(
+            Cursor::new(&data[..]),
+            |_| {},
+            |e| errors.push(e),
+            |rec| ids.push(rec.id.to_vec()),
+        )
+        .unwrap();
+        assert_eq!(ids, vec![b"A".to_vec()]);
+        assert_eq!(
+            errors[0].msg,
+            format!("Skipped {} bytes before the first record", BUF_SIZE + 11)
+        );
+
+        // and nothing but junk
+        let mut errors = Vec::new();
+        let res = parse_sequence_reader_lenient(
+            Cursor::new(&b"ACGT\nACGT\n"[..]),
+            |_| panic!("No file type"),
+            |e| errors.push(e),
+            |_| panic!("No records"),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(errors[0].msg, "Skipped 10 bytes before the first record");
+
+        // junk after whitespace that fills the first read of the stream
+        for read_size in &[1, 6, 4096] {
+            let mut ids = Vec::new();
+            let mut errors = Vec::new();
+            parse_sequence_reader_lenient(
+                ShortReads(b"\n\n\n\n\n\nACGT\n>a\nACGT\n", *read_size),
+                |_| {},
+                |e| errors.push(e),
+                |rec| ids.push(rec.id.to_vec()),
+            )
+            .unwrap();
+            assert_eq!(ids, vec![b"a".to_vec()]);
+            assert_eq!(errors[0].msg, "Skipped 11 bytes before the first record");
+        }
+    }
+
+    #[test]
+    fn test_alphabet() {
+        use super::{parse_sequence_reader_with_options, ParseOptions};
+        use crate::sequence::{DNA_ALPHABET, PROTEIN_ALPHABET};
+
+        let uniprot = b">sp|P69905|HBA_HUMAN Hemoglobin subunit alpha OS=Homo sapiens OX=9606 GN=HBA1 PE=1 SV=2
+MVLSPADKTNVKAAWGKVGAHAGEYGAEALERMFLSFPTTKTYFPHFDLSHGSAQVKGHGKK
+VADALTNAVAHVDDMPNALSALSDLHAHKLRVDPVNFKLLSHCLLVTLAAHLPAEFTPAVH
+ASLDKFLASVSTVLTSKYR
+";
+        let options = ParseOptions::new().alphabet(Some(PROTEIN_ALPHABET));
+        let mut n_records = 0;
+        let res = parse_sequence_reader_with_options(
+            Cursor::new(&uniprot[..]),
+            &options,
+            |_| {},
+            |_| n_records += 1,
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(n_records, 1);
+
+        let dna_options = ParseOptions::new().alphabet(Some(DNA_ALPHABET));
+        let res = parse_sequence_reader_with_options(
+            Cursor::new(&uniprot[..]),
+            &dna_options,
+            |_| {},
+            |_| {},
+        );
+        assert!(res.is_err());
+
+        let bad = b">good\nMVLSPADK\n>bad\nMVLS9ADK\n";
+        let e = parse_sequence_reader_with_options(Cursor::new(&bad[..]), &options, |_| {}, |_| {})
+            .unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::InvalidRecord);
+        assert_eq!(e.record, 2);
+        assert_eq!(e.context, "bad");
+        assert_eq!(
+            e.msg,
+            "Sequence has a character not in the alphabet ('9' at position 4)"
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_glob() {
+        use super::parse_sequence_glob;
+        use crate::util::TestDir;
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::fs;
+        use std::io::Write;
+
+        let dir = TestDir::new("glob");
+        let shards: &[(&str, &[u8])] = &[
+            ("sample.part001.fa.gz", b">c\nGG\n>d\nTT\n"),
+            ("sample.part000.fa.gz", b">a\nAC\n>b\nGT\n"),
+            ("other.part002.fa.gz", b">e\nAA\n"),
+        ];
+        for (name, data) in shards {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            fs::write(dir.join(name), encoder.finish().unwrap()).unwrap();
+        }
+
+        let mut ids = Vec::new();
+        let mut n_files = 0;
+        let res = parse_sequence_glob(
+            dir.join("sample.part*.fa.gz"),
+            |_| n_files += 1,
+            |rec| ids.push(rec.id.to_vec()),
+        );
+        assert_eq!(
+            res,
+            Ok(vec![
+                (dir.join("sample.part000.fa.gz"), 2),
+                (dir.join("sample.part001.fa.gz"), 2)
+            ])
+        );
+        assert_eq!(n_files, 2);
+        assert_eq!(
+            ids,
+            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
+        );
+
+        // record numbers carry on across files
+        fs::write(dir.join("sample.part002.fa"), b">e\nAA\n>f\n").unwrap();
+        let e = parse_sequence_glob(dir.join("sample.part*"), |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.record, 6);
+
+        let e = parse_sequence_glob(dir.join("nothing*"), |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.msg, "No files matched the pattern");
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_parse_many() {
+        use super::parse_many;
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let gzip = |data: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        };
+        let fasta = gzip(b">a\nACGT\n>b\nGG\n");
+        let fastq = gzip(b"@c\nTT\n+\nII\n");
+        let plain = b">d\nCC\n".to_vec();
+
+        let mut types = Vec::new();
+        let mut recs = Vec::new();
+        let res = parse_many(
+            vec![&fasta[..], &fastq[..], &plain[..]],
+            |file_type| types.push(file_type),
+            |rec| recs.push((rec.id.to_vec(), rec.qual.is_some())),
+        );
+        assert_eq!(res, Ok(vec![2, 1, 1]));
+        assert_eq!(types, vec!["FASTA", "FASTQ", "FASTA"]);
+        assert_eq!(
+            recs,
+            vec![
+                (b"a".to_vec(), false),
+                (b"b".to_vec(), false),
+                (b"c".to_vec(), true),
+                (b"d".to_vec(), false)
+            ]
+        );
+
+        // record numbers carry on across streams
+        let e = parse_many(vec![&fasta[..], &b"@e\nA\n+\n\n"[..]], |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.record, 3);
+
+        // an empty stream has no records
+        let res = parse_many(vec![&b""[..], &plain[..]], |_| {}, |_| {});
+        assert_eq!(res, Ok(vec![0, 1]));
+    }
+
+    #[test]
+    fn test_assume_raw_sequence() {
+        use super::{parse_sequence_reader_with_options, ParseOptions};
+
+        let options = ParseOptions::new().assume_raw_sequence(true);
+        let mut recs = Vec::new();
+        let mut file_types = Vec::new();
+        let res = parse_sequence_reader_with_options(
+            Cursor::new(b"ACGTACGT"),
+            &options,
+            |file_type| file_types.push(file_type),
+            |rec| recs.push(rec.into_owned()),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(file_types, vec!["FASTA"]);
+        assert_eq!(recs.len(), 1);
+        assert_eq!(&recs[0].id[..], b"sequence");
+        assert_eq!(&recs[0].seq[..], b"ACGTACGT");
+
+        // wrapped sequences are joined back together
+        let mut seqs = Vec::new();
+        parse_sequence_reader_with_options(
+            Cursor::new(b"acgt\r\nACGT\nAA\n"),
+            &options,
+            |_| {},
+            |rec| seqs.push(rec.seq.into_owned()),
+        )
+        .unwrap();
+        assert_eq!(seqs, vec![b"acgtACGTAA".to_vec()]);
+
+        // real FASTA is parsed as usual
+        let mut ids = Vec::new();
+        parse_sequence_reader_with_options(
+            Cursor::new(b">a\nACGT\n"),
+            &options,
+            |_| {},
+            |rec| ids.push(rec.id.into_owned()),
+        )
+        .unwrap();
+        assert_eq!(ids, vec![b"a".to_vec()]);
+
+        // and it's an error without the option
+        let e = parse_sequence_reader(Cursor::new(b"ACGTACGT"), |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.msg, "Could not detect file type");
+
+        // nothing's lost when the first read of the stream is only whitespace
+        for read_size in &[1, 6, 4096] {
+            let mut seqs = Vec::new();
+            parse_sequence_reader_with_options(
+                ShortReads(b"\n\n\n\n\n\nacgtacgtacgt\n", *read_size),
+                &options,
+                |_| {},
+                |rec| seqs.push(rec.seq.into_owned()),
+            )
+            .unwrap();
+            assert_eq!(seqs, vec![b"acgtacgtacgt".to_vec()]);
+        }
+    }
+
+    #[test]
+    fn test_case_mode() {
+        use super::{parse_sequence_reader_with_options, CaseMode, ParseOptions};
+
+        let parse = |data: &[u8], case| {
+            let mut seqs = Vec::new();
+            let options = ParseOptions::new().case(case);
+            parse_sequence_reader_with_options(
+                Cursor::new(data),
+                &options,
+                |_| {},
+                |rec| seqs.push(rec.seq.into_owned()),
+            )
+            .unwrap();
+            seqs
+        };
+
+        let fasta = b">a\nAcGt\nnN-\n>b\nacgt\n";
+        assert_eq!(
+            parse(fasta, CaseMode::Preserve),
+            vec![b"AcGtnN-".to_vec(), b"acgt".to_vec()]
+        );
+        assert_eq!(
+            parse(fasta, CaseMode::Upper),
+            vec![b"ACGTNN-".to_vec(), b"ACGT".to_vec()]
+        );
+        assert_eq!(
+            parse(fasta, CaseMode::Lower),
+            vec![b"acgtnn-".to_vec(), b"acgt".to_vec()]
+        );
+
+        let fastq = b"@a\nAcGt\n+\nIIII\n";
+        assert_eq!(parse(fastq, CaseMode::Preserve), vec![b"AcGt".to_vec()]);
+        assert_eq!(parse(fastq, CaseMode::Upper), vec![b"ACGT".to_vec()]);
+        assert_eq!(parse(fastq, CaseMode::Lower), vec![b"acgt".to_vec()]);
+    }
+
+    #[test]
+    fn test_unique_ids() {
+        use super::{parse_sequence_reader_with_options, ParseOptions};
+
+        let data = b">a first\nACGT\n>b\nAC\n>a second\nGG\n";
+        let res = parse_sequence_reader(Cursor::new(&data[..]), |_| {}, |_| {});
+        assert_eq!(res, Ok(()));
+
+        let options = ParseOptions::new().unique_ids(true);
+        let mut n_records = 0;
+        let e = parse_sequence_reader_with_options(
+            Cursor::new(&data[..]),
+            &options,
+            |_| {},
+            |_| n_records += 1,
+        )
+        .unwrap_err();
+        assert_eq!(n_records, 2);
+        assert_eq!(e.error_type, ParseErrorType::DuplicateId);
+        assert_eq!(e.record, 3);
+        assert_eq!(e.context, "a");
+        assert_eq!(e.msg, "Record 3 has the same id as record 1");
+
+        let data = b"@a\nA\n+\nI\n@b\nA\n+\nI\n";
+        let res =
+            parse_sequence_reader_with_options(Cursor::new(&data[..]), &options, |_| {}, |_| {});
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_max_record_len() {
+        use super::{parse_sequence_reader_with_options, ParseOptions, BUF_SIZE};
+
+        // each record is 15 bytes long
+        let data = b"@a\nACGT\n+\nIIII\n@b\nACGT\n+\nIIII\n";
+        let options = ParseOptions::new().max_record_len(Some(15));
+        let mut n_records = 0;
+        let res = parse_sequence_reader_with_options(
+            Cursor::new(&data[..]),
+            &options,
+            |_| {},
+            |_| n_records += 1,
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(n_records, 2);
+
+        let options = ParseOptions::new().max_record_len(Some(14));
+        let e =
+            parse_sequence_reader_with_options(Cursor::new(&data[..]), &options, |_| {}, |_| {})
+                .unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::RecordTooLarge);
+        assert_eq!(e.record, 1);
+
+        // a record too big for the read buffer errors before it's all read in
+        let mut data = b">huge\n".to_vec();
+        data.extend(vec![b'A'; 3 * BUF_SIZE]);
+        let options = ParseOptions::new().max_record_len(Some(1000));
+        let e = parse_sequence_reader_with_options(Cursor::new(data), &options, |_| {}, |_| {})
+            .unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::RecordTooLarge);
+        assert_eq!(e.record, 1);
+    }
+
+    #[test]
+    fn test_checksum() {
+        use super::{parse_sequence_reader_with_options, ParseOptions};
+
+        let data = b">a\nACGT\nAC\n>b\nACGA\n>a\nACGT\nAC";
+        let options = ParseOptions::new().checksum(true);
+        let mut checksums = Vec::new();
+        parse_sequence_reader_with_options(
+            Cursor::new(&data[..]),
+            &options,
+            |_| {},
+            |rec| checksums.push(rec.checksum.unwrap()),
+        )
+        .unwrap();
+        assert_eq!(checksums.len(), 3);
+        assert_eq!(checksums[0], checksums[2]);
+        assert_ne!(checksums[0], checksums[1]);
+        assert_eq!(checksums[0], crc32fast::hash(b">a\nACGT\nAC"));
+
+        // the same sequence wrapped differently is a different record
+        let data = b">a\nACG\nTAC\n";
+        parse_sequence_reader_with_options(
+            Cursor::new(&data[..]),
+            &options,
+            |_| {},
+            |rec| assert_ne!(rec.checksum.unwrap(), checksums[0]),
+        )
+        .unwrap();
+
+        let data = b"@a\nACGT\n+\nIIII\n";
+        parse_sequence_reader(
+            Cursor::new(&data[..]),
+            |_| {},
+            |rec| assert_eq!(rec.checksum, None),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_case_preserved() {
+        use crate::sequence::Sequence;
+
+        let mut i = 0;
+        let res = parse_sequence_reader(
+            Cursor::new(&b">a\nacGT\nNn\n>b\nurY\n"[..]),
+            |_| {},
+            |rec| {
+                let (raw, normalized) = match i {
+                    0 => (&b"acGTNn"[..], &b"ACGTNN"[..]),
+                    _ => (&b"urY"[..], &b"TNN"[..]),
+                };
+                assert_eq!(&rec.seq[..], raw);
+                assert_eq!(&rec.normalize(false)[..], normalized);
+                // normalizing made a copy, so the original is still there
+                assert_eq!(&rec.seq[..], raw);
+                i += 1;
+            },
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(i, 2);
+
+        parse_sequence_reader(
+            Cursor::new(&b"@a\nacgt\n+\nIIII\n"[..]),
+            |_| {},
+            |rec| assert_eq!(&rec.seq[..], b"acgt"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_utf8_bom() {
+        use super::SequenceReader;
+
+        let data = b"\xEF\xBB\xBF>test\nACGT\n>test2\nTGCA\n";
+        let mut i = 0;
+        let res = parse_sequence_reader(
+            Cursor::new(&data[..]),
+            |file_type| assert_eq!(file_type, "FASTA"),
+            |rec| {
+                assert_eq!(&rec.id[..], if i == 0 { &b"test"[..] } else { b"test2" });
+                i += 1;
+            },
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(i, 2);
+
+        let res = parse_sequence_bufread(
+            BufReader::new(Cursor::new(&data[..])),
+            |file_type| assert_eq!(file_type, "FASTA"),
+            |_| {},
+        );
+        assert_eq!(res, Ok(()));
+
+        let reader = SequenceReader::new(Cursor::new(&data[..])).unwrap();
+        assert_eq!(reader.count(), 2);
+
+        // a file that's only a byte order mark is empty
+        let res = parse_sequence_reader(Cursor::new(&b"\xEF\xBB\xBF"[..]), |_| {}, |_| {});
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_empty_input() {
+        for data in &[&b""[..], b"\n", b"  \r\n\n\t"] {
+            let res = parse_sequence_reader(
+                Cursor::new(data),
+                |_| panic!("There's no file type to detect"),
+                |_| panic!("There are no records"),
+            );
+            assert_eq!(res, Ok(()));
+
+            let res = parse_sequence_bufread(
+                BufReader::new(Cursor::new(data)),
+                |_| panic!("There's no file type to detect"),
+                |_| panic!("There are no records"),
+            );
+            assert_eq!(res, Ok(()));
+        }
+
+        // but whitespace before a record isn't allowed
+        let e = parse_sequence_reader(Cursor::new(b"\n>test\nACGT"), |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.msg, "Could not detect file type");
+        let reader = BufReader::new(Cursor::new(b"\n>test\nACGT"));
+        let e = parse_sequence_bufread(reader, |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.msg, "Could not detect file type");
+    }
+
+    #[test]
+    fn test_parse_matching() {
+        use super::{parse_matching, IdPattern};
+
+        let data = b">chr1 first\nAC\nGT\n>scaffold_chr2\nGG\n>chrX\nTT\n>chr10\nA\n";
+        let parse = |pattern: &IdPattern| {
+            let mut recs = Vec::new();
+            parse_matching(&data[..], pattern, |rec| {
+                recs.push((rec.id.to_vec(), rec.seq.to_vec()))
+            })
+            .unwrap();
+            recs
+        };
+
+        let recs = parse(&IdPattern::Glob(b"chr*".to_vec()));
+        assert_eq!(
+            recs,
+            vec![
+                (b"chr1 first".to_vec(), b"ACGT".to_vec()),
+                (b"chrX".to_vec(), b"TT".to_vec()),
+                (b"chr10".to_vec(), b"A".to_vec())
+            ]
+        );
+        let recs = parse(&IdPattern::Glob(b"chr?".to_vec()));
+        assert_eq!(recs.len(), 2);
+        let recs = parse(&IdPattern::Substring(b"chr".to_vec()));
+        assert_eq!(recs.len(), 4);
+        // only the id is matched, not the rest of the header
+        assert!(parse(&IdPattern::Substring(b"first".to_vec())).is_empty());
+
+        let mut n_records = 0;
+        let fq = b"@chr1\nA\n+\nI\n@x\nA\n+\nI\n";
+        parse_matching(&fq[..], &IdPattern::Glob(b"chr*".to_vec()), |_| {
+            n_records += 1
+        })
+        .unwrap();
+        assert_eq!(n_records, 1);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_parse_matching_regex() {
+        use super::{parse_matching, IdPattern};
+
+        let data = b">chr1\nA\n>chrUn_1\nA\n>chr22\nA\n";
+        let pattern = IdPattern::Regex(regex::bytes::Regex::new(r"^chr\d+$").unwrap());
+        let mut ids = Vec::new();
+        parse_matching(&data[..], &pattern, |rec| ids.push(rec.id.to_vec())).unwrap();
+        assert_eq!(ids, vec![b"chr1".to_vec(), b"chr22".to_vec()]);
+    }
+
+    #[test]
+    fn test_molecule_type_hint() {
+        use super::{molecule_type_hint, MoleculeType};
+        use crate::sequence::PROTEIN_ALPHABET;
+
+        let hint = molecule_type_hint("data/proteins.faa");
+        assert_eq!(hint, Some(MoleculeType::Protein));
+        assert_eq!(hint.unwrap().alphabet(), PROTEIN_ALPHABET);
+        assert_eq!(molecule_type_hint("x.FAA.bz2"), Some(MoleculeType::Protein));
+        assert_eq!(molecule_type_hint("x.fna"), Some(MoleculeType::Nucleotide));
+        assert_eq!(
+            molecule_type_hint("x.ffn.gz"),
+            Some(MoleculeType::Nucleotide)
+        );
+        assert_eq!(molecule_type_hint("x.fasta"), None);
+        assert_eq!(molecule_type_hint("x.gz"), None);
+        assert_eq!(molecule_type_hint("faa"), None);
+    }
+
+    #[test]
+    fn test_parse_sequence_path_with_info() {
+        use super::{parse_sequence_path_with_info, MoleculeType};
+        use crate::util::TestDir;
+        use std::fs;
+
+        let dir = TestDir::new("info");
+        fs::write(dir.join("proteins.faa"), b">p1\nMKV\n>p2\nMAL\n").unwrap();
+        let mut n_records = 0;
+        let info = parse_sequence_path_with_info(dir.join("proteins.faa"), |_| n_records += 1);
+        let info = info.unwrap();
+        assert_eq!(info.file_type, Some("FASTA"));
+        assert_eq!(info.molecule_type, Some(MoleculeType::Protein));
+        assert_eq!(n_records, 2);
+
+        fs::write(dir.join("reads.fq"), b"@r\nACGT\n+\nIIII\n").unwrap();
+        let info = parse_sequence_path_with_info(dir.join("reads.fq"), |_| {}).unwrap();
+        assert_eq!(info.file_type, Some("FASTQ"));
+        assert_eq!(info.molecule_type, None);
+
+        fs::write(dir.join("empty.fna"), b"").unwrap();
+        let info = parse_sequence_path_with_info(dir.join("empty.fna"), |_| {}).unwrap();
+        assert_eq!(info.file_type, None);
+        assert_eq!(info.molecule_type, Some(MoleculeType::Nucleotide));
+    }
+
+    #[test]
+    fn test_bufread_errors() {
+        let reader = BufReader::new(Cursor::new(b"This is not a valid FASTA file.\n"));
+        let e = parse_sequence_bufread(reader, |_| {}, |_| {}).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::InvalidHeader);
+
+        let reader = BufReader::with_capacity(4, Cursor::new(b"@A\nAC\n+\nII\n@B\nAC\n+\nI"));
+        let mut n_records = 0;
+        let e = parse_sequence_bufread(reader, |_| {}, |_| n_records += 1).unwrap_err();
+        assert_eq!(e.record, 2);
+        assert_eq!(n_records, 1);
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::{Read, Write};
+
+use crate::formats::reader::SequenceReader;
+use crate::sequence_record::SequenceRecord;
+use crate::util::{header_id, ParseError, ParseErrorType};
+
+/// The part of a read's id that's shared with its mate: everything before
+/// the first whitespace without any `/1` or `/2` suffix.
+fn pair_name(id: &[u8]) -> &[u8] {
+    let name = header_id(id);
+    if name.ends_with(b"/1") || name.ends_with(b"/2") {
+        &name[..name.len() - 2]
+    } else {
+        name
+    }
+}
+
+/// Read through an R1 and an R2 stream in lockstep, checking that they
+/// have the same number of records and that each pair has the same name.
+fn read_pairs<R1, R2, F>(r1: R1, r2: R2, mut pair_callback: F) -> Result<(), ParseError>
+where
+    R1: Read,
+    R2: Read,
+    F: FnMut(SequenceRecord<'static>, SequenceRecord<'static>) -> Result<(), ParseError>,
+{
+    let reader1 = SequenceReader::new(r1)?;
+    let mut reader2 = SequenceReader::new(r2)?;
+    let mut record_count = 0;
+    for rec1 in reader1 {
+        let rec1 = rec1?;
+        let rec2 = match reader2.next() {
+            Some(rec2) => rec2?,
+            None => {
+                return Err(ParseError::new(
+                    "R2 has fewer records than R1",
+                    ParseErrorType::PairLengthMismatch,
+                )
+                .record(record_count + 1));
+            }
+        };
+        record_count += 1;
+        if pair_name(&rec1.id) != pair_name(&rec2.id) {
+            let context = format!(
+                "{} / {}",
+                String::from_utf8_lossy(&rec1.id),
+                String::from_utf8_lossy(&rec2.id)
+            );
+            return Err(ParseError::new(
+                "Paired records have different names",
+                ParseErrorType::InvalidRecord,
+            )
+            .record(record_count)
+            .context(context));
+        }
+        pair_callback(rec1, rec2)?;
+    }
+    if let Some(rec2) = reader2.next() {
+        rec2?;
+        return Err(ParseError::new(
+            "R1 has fewer records than R2",
+            ParseErrorType::PairLengthMismatch,
+        )
+        .record(record_count + 1));
+    }
+    Ok(())
+}
+
+/// Parse an R1 and an R2 stream in lockstep, passing each pair of records
+/// to `pair_callback`. The streams have to contain the same number of
+/// records (a `PairLengthMismatch` error says which one ran out first) and
+/// each pair of records must have the same name (ignoring anything after
+/// whitespace and `/1` or `/2` suffixes).
+///
+/// Like `parse_sequence_reader`, compressed streams are transparently
+/// decompressed.
+pub fn parse_paired<R1, R2, F>(r1: R1, r2: R2, mut pair_callback: F) -> Result<(), ParseError>
+where
+    R1: Read,
+    R2: Read,
+    F: FnMut(SequenceRecord<'static>, SequenceRecord<'static>),
+{
+    read_pairs(r1, r2, |rec1, rec2| {
+        pair_callback(rec1, rec2);
+        Ok(())
+    })
+}
+
+/// Interleave the records from an R1 and an R2 stream into a single FASTQ,
+/// alternating between records from each. The streams are checked the same
+/// way as in `parse_paired`.
+///
+/// Records without quality scores (i.e. from FASTAs) are given fake ones
+/// (see `SequenceRecord::write_fastq`).
+pub fn interleave<R1, R2, W>(r1: R1, r2: R2, mut out: W) -> Result<(), ParseError>
+where
+    R1: Read,
+    R2: Read,
+    W: Write,
+{
+    read_pairs(r1, r2, |rec1, rec2| {
+        rec1.write_fastq(&mut out, b"\n")?;
+        rec2.write_fastq(&mut out, b"\n")?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::{interleave, parse_paired};
+    use crate::util::ParseErrorType;
+
+    #[test]
+    fn test_parse_paired() {
+        let r1 = Cursor::new(b"@A/1\nACGT\n+\nIIII\n@B/1 x\nGG\n+\n!!\n");
+        let r2 = Cursor::new(b">A/2\nTTTT\n>B/2 y\nCC\n");
+        let mut pairs = Vec::new();
+        parse_paired(r1, r2, |rec1, rec2| pairs.push((rec1, rec2))).unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(&pairs[0].0.seq[..], b"ACGT");
+        assert_eq!(&pairs[0].1.seq[..], b"TTTT");
+        assert_eq!(&pairs[1].0.id[..], b"B/1 x");
+        assert_eq!(&pairs[1].1.id[..], b"B/2 y");
+
+        // R2 is cut off part of the way through
+        let r1 = Cursor::new(b"@A/1\nACGT\n+\nIIII\n@B/1\nGG\n+\n!!\n");
+        let r2 = Cursor::new(b"@A/2\nTTTT\n+\n####\n");
+        let mut n_pairs = 0;
+        let e = parse_paired(r1, r2, |_, _| n_pairs += 1).unwrap_err();
+        assert_eq!(n_pairs, 1);
+        assert_eq!(e.error_type, ParseErrorType::PairLengthMismatch);
+        assert_eq!(e.msg, "R2 has fewer records than R1");
+        assert_eq!(e.record, 2);
+
+        let r1 = Cursor::new(b"@A/1\nACGT\n+\nIIII\n");
+        let r2 = Cursor::new(b"@A/2\nTTTT\n+\n####\n@B/2\nGG\n+\n!!\n");
+        let e = parse_paired(r1, r2, |_, _| {}).unwrap_err();
+        assert_eq!(e.msg, "R1 has fewer records than R2");
+
+        let r1 = Cursor::new(b"@A/1\nACGT\n+\nIIII\n");
+        let r2 = Cursor::new(b"@C/2\nTTTT\n+\n####\n");
+        let e = parse_paired(r1, r2, |_, _| {}).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::InvalidRecord);
+        assert_eq!(e.context, "A/1 / C/2");
+    }
+
+    #[test]
+    fn test_interleave() {
+        let r1 = Cursor::new(b"@A/1\nACGT\n+\nIIII\n@B/1 x\nGG\n+\n!!\n");
+        let r2 = Cursor::new(b"@A/2\nTTTT\n+\n####\n@B/2 y\nCC\n+\nII\n");
+        let mut out = Vec::new();
+        interleave(r1, r2, &mut out).unwrap();
+        assert_eq!(
+            &out[..],
+            &b"@A/1\nACGT\n+\nIIII\n@A/2\nTTTT\n+\n####\n@B/1 x\nGG\n+\n!!\n@B/2 y\nCC\n+\nII\n"[..]
+        );
+    }
+
+    #[test]
+    fn test_interleave_length_mismatch() {
+        let r1 = Cursor::new(b"@A/1\nACGT\n+\nIIII\n@B/1\nGG\n+\n!!\n");
+        let r2 = Cursor::new(b"@A/2\nTTTT\n+\n####\n");
+        let e = interleave(r1, r2, Vec::new()).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::PairLengthMismatch);
+        assert_eq!(e.record, 2);
+
+        let r1 = Cursor::new(b"");
+        let r2 = Cursor::new(b"@A/2\nTTTT\n+\n####\n");
+        let e = interleave(r1, r2, Vec::new()).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::PairLengthMismatch);
+        assert_eq!(e.record, 1);
+    }
+
+    #[test]
+    fn test_interleave_name_mismatch() {
+        let r1 = Cursor::new(b"@A/1\nACGT\n+\nIIII\n@B/1\nGG\n+\n!!\n");
+        let r2 = Cursor::new(b"@A/2\nTTTT\n+\n####\n@C/2\nCC\n+\nII\n");
+        let e = interleave(r1, r2, Vec::new()).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::InvalidRecord);
+        assert_eq!(e.record, 2);
+        assert_eq!(e.context, "B/1 / C/2");
+    }
+}
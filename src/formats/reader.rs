@@ -0,0 +1,384 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::mem;
+
+use crate::formats::buffer::{RecBuffer, RecParser};
+use crate::formats::fasta::{check_end, FastaParser};
+use crate::formats::fastq::FastqParser;
+use crate::formats::{decompress, detect_file_type, is_blank, is_compressed, read_start};
+use crate::sequence_record::SequenceRecord;
+use crate::util::{ParseError, ParseErrorType};
+
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// Where a `SequenceReader` reads from; an uncompressed seekable stream is
+/// kept (with the position it started at) so it can be rewound.
+enum Source<'r> {
+    Stream(Box<dyn Read + 'r>),
+    Seekable(Box<dyn ReadSeek + 'r>, u64),
+}
+
+impl<'r> Read for Source<'r> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Source::Stream(r) => r.read(buf),
+            Source::Seekable(r, _) => r.read(buf),
+        }
+    }
+}
+
+/// An iterator over the records in a FASTX stream.
+///
+/// Unlike the callback-based `parse_sequence_reader`, the records returned
+/// are owned (they're copied out of the internal buffer) so they can be kept
+/// around or read in lockstep with another stream. Like
+/// `parse_sequence_reader`, compressed streams are transparently
+/// decompressed and empty (or all whitespace) streams have no records.
+pub struct SequenceReader<'r> {
+    reader: Source<'r>,
+    buf: Vec<u8>,
+    pos: usize,
+    last: bool,
+    file_type: Option<&'static str>,
+    record_count: usize,
+    finished: bool,
+}
+
+impl<'r> SequenceReader<'r> {
+    /// Opens a `Read` stream and determines if it's a FASTA or FASTQ.
+    pub fn new<R: Read + 'r>(reader: R) -> Result<Self, ParseError> {
+        let (reader, buf) = decompress(reader)?;
+        SequenceReader::from_source(Source::Stream(reader), buf)
+    }
+
+    /// Like `new`, but for a seekable stream (e.g. a `File`) so that the
+    /// reader can be `rewind`ed back to the start for another pass over the
+    /// records. Compressed streams are still decompressed, but can't be
+    /// rewound.
+    pub fn new_seekable<R: Read + Seek + 'r>(mut reader: R) -> Result<Self, ParseError> {
+        let start = reader.stream_position()?;
+        let buf = read_start(&mut reader)?;
+        if is_compressed(&buf) {
+            reader.seek(SeekFrom::Start(start))?;
+            return SequenceReader::new(reader);
+        }
+        SequenceReader::from_source(Source::Seekable(Box::new(reader), start), buf)
+    }
+
+    fn from_source(mut reader: Source<'r>, mut buf: Vec<u8>) -> Result<Self, ParseError> {
+        let mut file_type = None;
+        let blank = buf.iter().all(u8::is_ascii_whitespace) && is_blank(&mut reader, &mut buf)?;
+        if !blank {
+            file_type = Some(detect_file_type(&buf)?);
+        }
+        Ok(SequenceReader {
+            reader,
+            buf,
+            pos: 0,
+            last: false,
+            file_type,
+            record_count: 0,
+            finished: blank,
+        })
+    }
+
+    /// Go back to the start of the stream (where it was when the reader was
+    /// created) so the records can be read again, e.g. for a two-pass
+    /// algorithm. Only readers made with `new_seekable` over an uncompressed
+    /// stream can be rewound.
+    pub fn rewind(&mut self) -> Result<(), ParseError> {
+        let buf = match &mut self.reader {
+            Source::Seekable(reader, start) => {
+                reader.seek(SeekFrom::Start(*start))?;
+                read_start(reader)?
+            }
+            Source::Stream(_) => {
+                return Err(ParseError::new(
+                    "Only uncompressed seekable streams can be rewound",
+                    ParseErrorType::Invalid,
+                ))
+            }
+        };
+        let reader = mem::replace(&mut self.reader, Source::Stream(Box::new(io::empty())));
+        *self = SequenceReader::from_source(reader, buf)?;
+        Ok(())
+    }
+
+    /// The type of records in the stream ("FASTA" or "FASTQ") or `None` if
+    /// the stream is empty.
+    pub fn file_type(&self) -> Option<&'static str> {
+        self.file_type
+    }
+
+    /// Try to parse a record out of what's currently in the buffer;
+    /// returns the record (if there's a complete one) and how many bytes of
+    /// the buffer it took up.
+    fn parse_record(&self) -> (Option<Result<SequenceRecord<'static>, ParseError>>, usize) {
+        let buf = &self.buf[self.pos..];
+        match self.file_type {
+            Some("FASTA") => {
+                let mut parser = FastaParser::from_buffer(buf, self.last);
+                let rec = parser
+                    .next()
+                    .map(|r| r.map(|r| SequenceRecord::from(r).into_owned()));
+                (rec, parser.used())
+            }
+            Some("FASTQ") => {
+                let mut parser = FastqParser::from_buffer(buf, self.last);
+                let rec = parser
+                    .next()
+                    .map(|r| r.map(|r| SequenceRecord::from(r).into_owned()));
+                (rec, parser.used())
+            }
+            _ => panic!("A file type was inferred that could not be parsed"),
+        }
+    }
+
+    /// Drop the parsed records from the buffer and read more data in.
+    fn refill(&mut self) -> Result<(), ParseError> {
+        let mut buffer = RecBuffer::new(&mut self.reader, mem::take(&mut self.buf))?;
+        buffer.last = self.last;
+        buffer.refill(self.pos)?;
+        self.buf = buffer.buf;
+        self.last = buffer.last;
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl<'r> Iterator for SequenceReader<'r> {
+    type Item = Result<SequenceRecord<'static>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            match self.parse_record() {
+                (Some(Ok(rec)), used) => {
+                    self.pos += used;
+                    self.record_count += 1;
+                    return Some(Ok(rec));
+                }
+                (Some(Err(e)), _) => {
+                    self.finished = true;
+                    return Some(Err(e.record(self.record_count + 1)));
+                }
+                (None, _) if self.last => {
+                    self.finished = true;
+                    return match check_end(&self.buf[self.pos..], true) {
+                        Ok(()) => None,
+                        Err(e) => Some(Err(e.record(self.record_count + 1))),
+                    };
+                }
+                (None, _) => {
+                    // there's not a whole record in the buffer so read more in
+                    if let Err(e) = self.refill() {
+                        self.finished = true;
+                        return Some(Err(e.record(self.record_count)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over batches of records; see `batched`.
+pub struct Batches<'r> {
+    reader: SequenceReader<'r>,
+    batch_size: usize,
+    error: Option<ParseError>,
+}
+
+/// Read the records in a FASTX stream in batches of `batch_size` (the last
+/// batch can be smaller), e.g. to hand them off to vectorized code a batch
+/// at a time. If a record can't be parsed, the records before it are
+/// returned as a (short) batch and then the error is.
+///
+/// # Panics
+///
+/// If `batch_size` is 0.
+///
+/// ```
+/// use needletail::formats::batched;
+///
+/// let batches = batched(&b">a\nA\n>b\nC\n>c\nG\n"[..], 2).unwrap();
+/// let sizes: Vec<usize> = batches.map(|b| b.unwrap().len()).collect();
+/// assert_eq!(sizes, vec![2, 1]);
+/// ```
+pub fn batched<'r, R: Read + 'r>(reader: R, batch_size: usize) -> Result<Batches<'r>, ParseError> {
+    assert!(batch_size > 0, "batch_size must be at least 1");
+    Ok(Batches {
+        reader: SequenceReader::new(reader)?,
+        batch_size,
+        error: None,
+    })
+}
+
+impl<'r> Iterator for Batches<'r> {
+    type Item = Result<Vec<SequenceRecord<'static>>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        let mut batch = Vec::with_capacity(self.batch_size);
+        while batch.len() < self.batch_size {
+            match self.reader.next() {
+                Some(Ok(rec)) => batch.push(rec),
+                Some(Err(e)) if batch.is_empty() => return Some(Err(e)),
+                Some(Err(e)) => {
+                    self.error = Some(e);
+                    break;
+                }
+                None => break,
+            }
+        }
+        if batch.is_empty() {
+            None
+        } else {
+            Some(Ok(batch))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::io::Cursor;
+
+    use super::{batched, SequenceReader};
+    use crate::formats::parse_sequence_reader;
+    use crate::util::ParseErrorType;
+
+    #[test]
+    fn test_reader() {
+        let reader = SequenceReader::new(Cursor::new(b">test\nAGCT\n>test2\nGA\nTC")).unwrap();
+        assert_eq!(reader.file_type(), Some("FASTA"));
+        let recs: Vec<_> = reader.map(|r| r.unwrap()).collect();
+        assert_eq!(recs.len(), 2);
+        assert_eq!(&recs[0].id[..], b"test");
+        assert_eq!(&recs[0].seq[..], b"AGCT");
+        assert_eq!(&recs[1].id[..], b"test2");
+        assert_eq!(&recs[1].seq[..], b"GATC");
+
+        let mut reader = SequenceReader::new(Cursor::new(b"")).unwrap();
+        assert_eq!(reader.file_type(), None);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_reader_matches_callback() {
+        for path in &["./tests/data/28S.fasta", "./tests/data/PRJNA271013_head.fq"] {
+            let mut expected = Vec::new();
+            parse_sequence_reader(
+                File::open(path).unwrap(),
+                |_| {},
+                |rec| expected.push(rec.into_owned()),
+            )
+            .unwrap();
+
+            let reader = SequenceReader::new(File::open(path).unwrap()).unwrap();
+            let recs: Vec<_> = reader.map(|r| r.unwrap()).collect();
+            assert_eq!(recs.len(), expected.len());
+            for (rec, exp) in recs.iter().zip(expected.iter()) {
+                assert_eq!(rec.id, exp.id);
+                assert_eq!(rec.seq, exp.seq);
+                assert_eq!(rec.qual, exp.qual);
+            }
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_reader_compressed() {
+        let reader = SequenceReader::new(File::open("./tests/data/test.fa.gz").unwrap()).unwrap();
+        let recs: Vec<_> = reader.map(|r| r.unwrap()).collect();
+        assert_eq!(recs.len(), 2);
+        assert_eq!(&recs[1].seq[..], b"TAGC");
+    }
+
+    #[test]
+    fn test_rewind() {
+        let path = "./tests/data/PRJNA271013_head.fq";
+        let mut reader = SequenceReader::new_seekable(File::open(path).unwrap()).unwrap();
+        let n_records = reader.by_ref().count();
+        assert!(n_records > 0);
+
+        reader.rewind().unwrap();
+        assert_eq!(reader.file_type(), Some("FASTQ"));
+        let recs: Vec<_> = reader.by_ref().map(|r| r.unwrap()).collect();
+        assert_eq!(recs.len(), n_records);
+        let mut expected = Vec::new();
+        parse_sequence_reader(
+            File::open(path).unwrap(),
+            |_| {},
+            |rec| expected.push(rec.into_owned()),
+        )
+        .unwrap();
+        assert_eq!(recs, expected);
+
+        // rewinding part of the way through goes back to where the reader
+        // started (not necessarily the start of the stream)
+        let mut cursor = Cursor::new(&b"junk>a\nAC\n>b\nGT\n"[..]);
+        cursor.set_position(4);
+        let mut reader = SequenceReader::new_seekable(cursor).unwrap();
+        assert_eq!(&reader.next().unwrap().unwrap().id[..], b"a");
+        reader.rewind().unwrap();
+        assert_eq!(reader.count(), 2);
+
+        let mut reader = SequenceReader::new(File::open(path).unwrap()).unwrap();
+        assert_eq!(
+            reader.rewind().unwrap_err().error_type,
+            ParseErrorType::Invalid
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_rewind_compressed() {
+        let file = File::open("./tests/data/test.fa.gz").unwrap();
+        let mut reader = SequenceReader::new_seekable(file).unwrap();
+        assert_eq!(reader.by_ref().count(), 2);
+        let e = reader.rewind().unwrap_err();
+        assert_eq!(e.msg, "Only uncompressed seekable streams can be rewound");
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        let res = SequenceReader::new(Cursor::new(b"This is not a valid FASTA file."));
+        assert_eq!(res.err().unwrap().error_type, ParseErrorType::InvalidHeader);
+
+        let mut reader = SequenceReader::new(Cursor::new(b"@A\nAC\n+\nII\n@B\nAC\n+\nI")).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        let e = reader.next().unwrap().unwrap_err();
+        assert_eq!(e.record, 2);
+        // and the iterator stops after an error
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_batched() {
+        let mut data = Vec::new();
+        for i in 0..10 {
+            data.extend(format!("@read{}\nACGT\n+\nIIII\n", i).into_bytes());
+        }
+        let batches: Vec<_> = batched(Cursor::new(&data), 4)
+            .unwrap()
+            .map(|b| b.unwrap())
+            .collect();
+        let sizes: Vec<_> = batches.iter().map(|b| b.len()).collect();
+        assert_eq!(sizes, vec![4, 4, 2]);
+        assert_eq!(&batches[1][0].id[..], b"read4");
+        assert_eq!(&batches[2][1].id[..], b"read9");
+
+        assert_eq!(batched(Cursor::new(b""), 4).unwrap().count(), 0);
+
+        // the records before a bad one still come out
+        let mut batches = batched(Cursor::new(b">a\nA\n>b\nC\n>\n"), 4).unwrap();
+        assert_eq!(batches.next().unwrap().unwrap().len(), 2);
+        assert_eq!(batches.next().unwrap().unwrap_err().record, 3);
+        assert!(batches.next().is_none());
+    }
+}
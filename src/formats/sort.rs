@@ -0,0 +1,284 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{self, AtomicUsize};
+
+use crate::formats::reader::SequenceReader;
+use crate::formats::writer::{SequenceFormat, SequenceWriter};
+use crate::sequence_record::SequenceRecord;
+use crate::util::ParseError;
+
+/// How many bytes of records `sort_records` keeps in memory before spilling
+/// a sorted chunk of them to disk
+const DEFAULT_CHUNK_BYTES: usize = 512 * 1024 * 1024;
+
+/// What `sort_records` sorts records by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    /// The length of the sequence
+    Length,
+    /// The record's id (its whole header), compared byte by byte
+    Id,
+}
+
+/// Which way `sort_records` sorts records
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+fn compare(a: &SequenceRecord, b: &SequenceRecord, key: SortKey, order: Order) -> Ordering {
+    let ordering = match key {
+        SortKey::Length => a.seq.len().cmp(&b.seq.len()),
+        SortKey::Id => a.id.cmp(&b.id),
+    };
+    match order {
+        Order::Ascending => ordering,
+        Order::Descending => ordering.reverse(),
+    }
+}
+
+/// The next record from one of the sorted chunks being merged
+struct Head {
+    rec: SequenceRecord<'static>,
+    chunk: usize,
+    key: SortKey,
+    order: Order,
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    // reversed so the `BinaryHeap` pops the record that sorts first (with
+    // ties going to the earlier chunk to keep the sort stable)
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(&other.rec, &self.rec, self.key, self.order).then(other.chunk.cmp(&self.chunk))
+    }
+}
+
+/// A directory of sorted chunks that's removed when the sort's done (or
+/// fails)
+struct ChunkDir(PathBuf);
+
+impl ChunkDir {
+    fn new() -> Result<Self, ParseError> {
+        static N_SORTS: AtomicUsize = AtomicUsize::new(0);
+        let n = N_SORTS.fetch_add(1, atomic::Ordering::SeqCst);
+        let dir =
+            std::env::temp_dir().join(format!("needletail-sort-{}-{}", std::process::id(), n));
+        fs::create_dir_all(&dir)?;
+        Ok(ChunkDir(dir))
+    }
+
+    fn chunk(&self, i: usize) -> PathBuf {
+        self.0.join(format!("chunk_{}", i))
+    }
+}
+
+impl Drop for ChunkDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Read every record in a stream and write them back out to `writer` (in
+/// the same format) sorted by `key`, e.g. longest first for an assembler.
+/// The sort is stable, so records that tie keep the order they were in.
+///
+/// Input that's too big to sort in memory is sorted in chunks that are
+/// written to the system's temporary directory and then merged, so huge
+/// files can be sorted without running out of memory.
+///
+/// ```
+/// use needletail::formats::{sort_records, Order, SortKey};
+///
+/// let mut out = Vec::new();
+/// sort_records(&b">a\nAC\n>b\nACGT\n"[..], &mut out, SortKey::Length, Order::Descending)
+///     .unwrap();
+/// assert_eq!(&out[..], b">b\nACGT\n>a\nAC\n");
+/// ```
+pub fn sort_records<R, W>(
+    reader: R,
+    writer: W,
+    key: SortKey,
+    order: Order,
+) -> Result<(), ParseError>
+where
+    R: Read,
+    W: Write,
+{
+    sort_records_in_chunks(reader, writer, key, order, DEFAULT_CHUNK_BYTES)
+}
+
+/// Like `sort_records`, but only keeps about `chunk_bytes` of records in
+/// memory at a time
+pub fn sort_records_in_chunks<R, W>(
+    reader: R,
+    writer: W,
+    key: SortKey,
+    order: Order,
+    chunk_bytes: usize,
+) -> Result<(), ParseError>
+where
+    R: Read,
+    W: Write,
+{
+    let mut reader = SequenceReader::new(reader)?;
+    if reader.file_type().is_none() {
+        return Ok(());
+    }
+    let format = SequenceFormat::from_file_type(reader.file_type());
+
+    let mut chunk_dir = None;
+    let mut n_chunks = 0;
+    let mut records = Vec::new();
+    loop {
+        let mut size = 0;
+        for rec in reader.by_ref() {
+            let rec = rec?;
+            size += rec.id.len() + rec.seq.len() + rec.qual.as_ref().map_or(0, |q| q.len());
+            records.push(rec);
+            if size >= chunk_bytes {
+                break;
+            }
+        }
+        records.sort_by(|a, b| compare(a, b, key, order));
+        if size < chunk_bytes && n_chunks == 0 {
+            // everything fit in memory, so there's nothing to merge
+            let mut writer = SequenceWriter::new(writer, format);
+            for rec in &records {
+                writer.write(rec)?;
+            }
+            return writer.finish();
+        }
+        if records.is_empty() {
+            break;
+        }
+
+        if chunk_dir.is_none() {
+            chunk_dir = Some(ChunkDir::new()?);
+        }
+        let path = chunk_dir.as_ref().unwrap().chunk(n_chunks);
+        let mut chunk_writer = SequenceWriter::new(BufWriter::new(File::create(path)?), format);
+        for rec in records.drain(..) {
+            chunk_writer.write(&rec)?;
+        }
+        chunk_writer.finish()?;
+        n_chunks += 1;
+    }
+
+    let chunk_dir = chunk_dir.unwrap();
+    let mut chunks = Vec::with_capacity(n_chunks);
+    let mut heap = BinaryHeap::with_capacity(n_chunks);
+    for chunk in 0..n_chunks {
+        let file = BufReader::new(File::open(chunk_dir.chunk(chunk))?);
+        let mut chunk_reader = SequenceReader::new(file)?;
+        if let Some(rec) = chunk_reader.next() {
+            heap.push(Head {
+                rec: rec?,
+                chunk,
+                key,
+                order,
+            });
+        }
+        chunks.push(chunk_reader);
+    }
+    let mut writer = SequenceWriter::new(writer, format);
+    while let Some(head) = heap.pop() {
+        writer.write(&head.rec)?;
+        if let Some(rec) = chunks[head.chunk].next() {
+            heap.push(Head { rec: rec?, ..head });
+        }
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sort_records, sort_records_in_chunks, Order, SortKey};
+    use crate::formats::parse_sequence_reader;
+
+    fn parse_ids(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut ids = Vec::new();
+        parse_sequence_reader(data, |_| {}, |rec| ids.push(rec.id.to_vec())).unwrap();
+        ids
+    }
+
+    #[test]
+    fn test_sort_records() {
+        let data = b">a\nACG\n>b\nACGTACGT\n>c\nA\n>d\nAC\nGTA\n>e\nACG\n";
+        let mut out = Vec::new();
+        sort_records(&data[..], &mut out, SortKey::Length, Order::Descending).unwrap();
+        // ties (a and e) stay in the order they were in
+        assert_eq!(
+            parse_ids(&out),
+            vec![
+                b"b".to_vec(),
+                b"d".to_vec(),
+                b"a".to_vec(),
+                b"e".to_vec(),
+                b"c".to_vec()
+            ]
+        );
+        assert_eq!(&out[..11], b">b\nACGTACGT");
+
+        let mut out = Vec::new();
+        sort_records(
+            &b"@z\nA\n+\nI\n@y\nG\n+\n#\n"[..],
+            &mut out,
+            SortKey::Id,
+            Order::Ascending,
+        )
+        .unwrap();
+        assert_eq!(&out[..], b"@y\nG\n+\n#\n@z\nA\n+\nI\n");
+
+        let mut out = Vec::new();
+        sort_records(&b""[..], &mut out, SortKey::Id, Order::Ascending).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_sort_records_on_disk() {
+        let mut data = Vec::new();
+        let mut expected = Vec::new();
+        for i in 0..100 {
+            let len = (i * 37) % 101 + 1;
+            data.extend(format!(">r{}\n{}\n", i, "A".repeat(len)).into_bytes());
+            expected.push((len, format!("r{}", i).into_bytes()));
+        }
+        // a stable descending sort by length
+        expected.sort_by_key(|(len, _)| std::cmp::Reverse(*len));
+        let expected: Vec<Vec<u8>> = expected.into_iter().map(|(_, id)| id).collect();
+
+        // small enough chunks that there are lots of them to merge
+        let mut out = Vec::new();
+        sort_records_in_chunks(&data[..], &mut out, SortKey::Length, Order::Descending, 200)
+            .unwrap();
+        assert_eq!(parse_ids(&out), expected);
+
+        let mut in_memory = Vec::new();
+        sort_records(
+            &data[..],
+            &mut in_memory,
+            SortKey::Length,
+            Order::Descending,
+        )
+        .unwrap();
+        assert_eq!(out, in_memory);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Read};
+use std::path::Path;
+
+use crate::formats::reader::SequenceReader;
+use crate::formats::writer::{SequenceFormat, SequenceWriter};
+use crate::util::{fnv1a_64, header_id, ParseError, ParseErrorType};
+
+/// Split the records in a stream into `n_bins` files in `out_dir` (named
+/// `bin_0.fa`, `bin_1.fa`, ... or `.fq` for FASTQ input) by hashing each
+/// record's id (everything before the first whitespace). The hash is stable,
+/// so a record always ends up in the same bin no matter which run, platform
+/// or file it came from. Returns the number of records written to each bin.
+///
+/// No files are written if the stream is empty.
+pub fn split_by_hash<R, P>(reader: R, n_bins: usize, out_dir: P) -> Result<Vec<usize>, ParseError>
+where
+    R: Read,
+    P: AsRef<Path>,
+{
+    if n_bins == 0 {
+        return Err(ParseError::new(
+            "Records must be split into at least one bin",
+            ParseErrorType::Invalid,
+        ));
+    }
+    let reader = SequenceReader::new(reader)?;
+    let mut counts = vec![0; n_bins];
+    if reader.file_type().is_none() {
+        return Ok(counts);
+    }
+    let format = SequenceFormat::from_file_type(reader.file_type());
+    let ext = match format {
+        SequenceFormat::Fastq => "fq",
+        SequenceFormat::Fasta => "fa",
+    };
+
+    let out_dir = out_dir.as_ref();
+    fs::create_dir_all(out_dir)?;
+    let mut writers = Vec::with_capacity(n_bins);
+    for bin in 0..n_bins {
+        let file = File::create(out_dir.join(format!("bin_{}.{}", bin, ext)))?;
+        writers.push(SequenceWriter::new(BufWriter::new(file), format));
+    }
+
+    for rec in reader {
+        let rec = rec?;
+        let id = header_id(&rec.id);
+        let bin = (fnv1a_64(id) % n_bins as u64) as usize;
+        writers[bin].write(&rec)?;
+        counts[bin] += 1;
+    }
+    for writer in writers {
+        writer.finish()?;
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::split_by_hash;
+    use crate::formats::parse_sequence_path;
+    use crate::util::TestDir;
+
+    #[test]
+    fn test_split_by_hash() {
+        let mut data = Vec::new();
+        for i in 0..50 {
+            data.extend(format!(">read{} sample\nACGT\n", i).into_bytes());
+        }
+
+        let dir = TestDir::new("split");
+        let counts = split_by_hash(&data[..], 4, dir.join("a")).unwrap();
+        assert_eq!(counts.iter().sum::<usize>(), 50);
+        assert!(counts.iter().all(|c| *c > 0));
+
+        // every record in a bin is there because of its id
+        let mut n_records = 0;
+        parse_sequence_path(
+            dir.join("a").join("bin_2.fa"),
+            |_| {},
+            |rec| {
+                assert!(rec.id.ends_with(b" sample"));
+                n_records += 1;
+            },
+        )
+        .unwrap();
+        assert_eq!(n_records, counts[2]);
+
+        // the bins are the same if we run it again
+        assert_eq!(split_by_hash(&data[..], 4, dir.join("b")).unwrap(), counts);
+        for bin in 0..4 {
+            let name = format!("bin_{}.fa", bin);
+            assert_eq!(
+                fs::read(dir.join("a").join(&name)).unwrap(),
+                fs::read(dir.join("b").join(&name)).unwrap()
+            );
+        }
+
+        assert!(split_by_hash(&data[..], 0, dir.join("c")).is_err());
+    }
+}
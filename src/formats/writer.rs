@@ -0,0 +1,536 @@
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+#[cfg(feature = "compression")]
+use std::mem;
+
+#[cfg(feature = "compression")]
+use std::fs::File;
+#[cfg(feature = "compression")]
+use std::io::BufWriter;
+#[cfg(feature = "compression")]
+use std::path::Path;
+
+#[cfg(feature = "compression")]
+use bzip2::write::BzEncoder;
+#[cfg(feature = "compression")]
+use flate2::write::GzEncoder;
+#[cfg(feature = "compression")]
+use xz2::write::XzEncoder;
+
+use crate::formats::reader::SequenceReader;
+use crate::formats::{decompress, detect_file_type, is_blank};
+use crate::sequence_record::SequenceRecord;
+#[cfg(feature = "compression")]
+use crate::util::fnv1a_64;
+use crate::util::{header_id, ParseError};
+
+/// When writing rsyncable gzip, a new gzip member is started after about one
+/// in this many records
+#[cfg(feature = "compression")]
+const RSYNC_RECORDS: u64 = 64;
+
+/// The format a `SequenceWriter` writes records out as
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SequenceFormat {
+    Fasta,
+    Fastq,
+}
+
+impl SequenceFormat {
+    /// The format to write records out in to match what a
+    /// `SequenceReader` is reading (FASTA if it hasn't got any records)
+    pub(crate) fn from_file_type(file_type: Option<&str>) -> Self {
+        match file_type {
+            Some("FASTQ") => SequenceFormat::Fastq,
+            _ => SequenceFormat::Fasta,
+        }
+    }
+}
+
+/// Where a `SequenceWriter`'s output goes; the encoders are kept around
+/// (instead of being boxed up) so they can be explicitly finished.
+enum Output<'w> {
+    Plain(Box<dyn Write + 'w>),
+    #[cfg(feature = "compression")]
+    Gz(GzEncoder<BufWriter<File>>, flate2::Compression),
+    #[cfg(feature = "compression")]
+    Bz(BzEncoder<BufWriter<File>>),
+    #[cfg(feature = "compression")]
+    Xz(XzEncoder<BufWriter<File>>),
+}
+
+impl<'w> Write for Output<'w> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(w) => w.write(buf),
+            #[cfg(feature = "compression")]
+            Output::Gz(w, _) => w.write(buf),
+            #[cfg(feature = "compression")]
+            Output::Bz(w) => w.write(buf),
+            #[cfg(feature = "compression")]
+            Output::Xz(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(w) => w.flush(),
+            #[cfg(feature = "compression")]
+            Output::Gz(w, _) => w.flush(),
+            #[cfg(feature = "compression")]
+            Output::Bz(w) => w.flush(),
+            #[cfg(feature = "compression")]
+            Output::Xz(w) => w.flush(),
+        }
+    }
+}
+
+/// Writes SequenceRecords out to a stream as FASTA or FASTQ (with `\n` line
+/// endings).
+pub struct SequenceWriter<'w> {
+    out: Output<'w>,
+    format: SequenceFormat,
+    repeat_id_on_plus: bool,
+    rsyncable: bool,
+}
+
+impl<'w> SequenceWriter<'w> {
+    /// Write records (uncompressed) to any `Write` stream
+    pub fn new<W: Write + 'w>(writer: W, format: SequenceFormat) -> Self {
+        SequenceWriter {
+            out: Output::Plain(Box::new(writer)),
+            format,
+            repeat_id_on_plus: false,
+            rsyncable: false,
+        }
+    }
+
+    /// Repeat each record's id on the `+` line of FASTQ output (off by
+    /// default; needletail reads FASTQs either way)
+    pub fn repeat_id_on_plus(mut self, repeat: bool) -> Self {
+        self.repeat_id_on_plus = repeat;
+        self
+    }
+
+    /// Make gzip output (from `write_compressed`) "rsyncable" (off by
+    /// default): it's split into independent gzip members that always
+    /// start at the same records (picked by hashing their ids), so a small
+    /// change to the records only changes a small part of the compressed
+    /// file, e.g. for keeping references in version control. This costs a
+    /// little compression. Other outputs are unaffected.
+    pub fn rsyncable(mut self, rsyncable: bool) -> Self {
+        self.rsyncable = rsyncable;
+        self
+    }
+
+    /// Write out a single record. Records without quality scores written as
+    /// FASTQ are given fake ones (see `SequenceRecord::write_fastq`).
+    pub fn write(&mut self, rec: &SequenceRecord) -> Result<(), ParseError> {
+        match self.format {
+            SequenceFormat::Fasta => rec.write_fasta(&mut self.out, b"\n")?,
+            SequenceFormat::Fastq => {
+                rec.write_fastq_with(&mut self.out, b"\n", self.repeat_id_on_plus)?
+            }
+        }
+        #[cfg(feature = "compression")]
+        {
+            if self.rsyncable && fnv1a_64(&rec.id).is_multiple_of(RSYNC_RECORDS) {
+                self.restart_gzip_member()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish off the current gzip member (if the output's gzipped) and
+    /// start a new one
+    #[cfg(feature = "compression")]
+    fn restart_gzip_member(&mut self) -> Result<(), ParseError> {
+        self.out = match mem::replace(&mut self.out, Output::Plain(Box::new(io::sink()))) {
+            Output::Gz(w, level) => Output::Gz(GzEncoder::new(w.finish()?, level), level),
+            out => out,
+        };
+        Ok(())
+    }
+
+    /// Flush everything out and finish off any compression stream. Dropping
+    /// the writer also does this, but any errors are lost.
+    pub fn finish(self) -> Result<(), ParseError> {
+        match self.out {
+            Output::Plain(mut w) => w.flush()?,
+            #[cfg(feature = "compression")]
+            Output::Gz(w, _) => w.finish()?.flush()?,
+            #[cfg(feature = "compression")]
+            Output::Bz(w) => w.finish()?.flush()?,
+            #[cfg(feature = "compression")]
+            Output::Xz(w) => w.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+/// Create a file to write records to, compressing them based on the file's
+/// extension (`.gz`, `.bz2` or `.xz`; anything else is left uncompressed).
+/// `level` is the compression level from 1 (fastest) to 9 (smallest); bzip2
+/// only has fast, default and best levels so it's rounded to one of those.
+#[cfg(feature = "compression")]
+pub fn write_compressed<P: AsRef<Path>>(
+    path: P,
+    format: SequenceFormat,
+    level: u32,
+) -> Result<SequenceWriter<'static>, ParseError> {
+    let path = path.as_ref();
+    let file = BufWriter::new(File::create(path)?);
+    let out = match path.extension().and_then(|e| e.to_str()) {
+        Some("gz") => {
+            let level = flate2::Compression::new(level);
+            Output::Gz(GzEncoder::new(file, level), level)
+        }
+        Some("bz2") => {
+            let level = match level {
+                0..=3 => bzip2::Compression::Fastest,
+                4..=6 => bzip2::Compression::Default,
+                _ => bzip2::Compression::Best,
+            };
+            Output::Bz(BzEncoder::new(file, level))
+        }
+        Some("xz") => Output::Xz(XzEncoder::new(file, level)),
+        _ => Output::Plain(Box::new(file)),
+    };
+    Ok(SequenceWriter {
+        out,
+        format,
+        repeat_id_on_plus: false,
+        rsyncable: false,
+    })
+}
+
+/// Parse a stream, writing a copy of every record out to each of `writers`
+/// (in the same format as the input) before passing it on to `callback`,
+/// e.g. to archive records while processing them without reading the
+/// input twice.
+pub fn parse_and_tee<'w, R, F>(
+    reader: R,
+    writers: Vec<Box<dyn Write + 'w>>,
+    mut callback: F,
+) -> Result<(), ParseError>
+where
+    R: Read,
+    F: FnMut(SequenceRecord<'static>),
+{
+    let reader = SequenceReader::new(reader)?;
+    let format = SequenceFormat::from_file_type(reader.file_type());
+    let mut writers: Vec<SequenceWriter> = writers
+        .into_iter()
+        .map(|w| SequenceWriter::new(w, format))
+        .collect();
+    for rec in reader {
+        let rec = rec?;
+        for writer in writers.iter_mut() {
+            writer.write(&rec)?;
+        }
+        callback(rec);
+    }
+    for writer in writers {
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+/// Copy the records in a stream to `writer` (in the same format), dropping
+/// any record with the same id (everything in the header before the first
+/// whitespace) as one that came before it; the first record with each id is
+/// kept. Every id is kept in memory. Returns how many records were dropped.
+///
+/// Unlike `ParseOptions::unique_ids`, duplicates aren't an error.
+pub fn dedup_by_id<R, W>(reader: R, writer: W) -> Result<usize, ParseError>
+where
+    R: Read,
+    W: Write,
+{
+    let reader = SequenceReader::new(reader)?;
+    let format = SequenceFormat::from_file_type(reader.file_type());
+    let mut writer = SequenceWriter::new(writer, format);
+    let mut seen_ids = HashSet::new();
+    let mut n_dropped = 0;
+    for rec in reader {
+        let rec = rec?;
+        let id = header_id(&rec.id);
+        if seen_ids.contains(id) {
+            n_dropped += 1;
+            continue;
+        }
+        seen_ids.insert(id.to_vec());
+        writer.write(&rec)?;
+    }
+    writer.finish()?;
+    Ok(n_dropped)
+}
+
+/// Copy the records in a stream to `writer` (in the same format), passing
+/// every (raw, so e.g. Phred+33) quality score through `f` on the way, e.g.
+/// for recalibrating or binning them. FASTA has no quality scores, so it's
+/// copied through exactly as it is (after being decompressed, if it was).
+/// FASTQ ids and sequences are kept as they are, but each record is
+/// written out on four lines.
+///
+/// ```
+/// use needletail::formats::map_quality;
+///
+/// let mut out = Vec::new();
+/// map_quality(&b"@a\nACGT\n+\n#5?I\n"[..], &mut out, |q| q.max(b'+')).unwrap();
+/// assert_eq!(&out[..], b"@a\nACGT\n+\n+5?I\n");
+/// ```
+pub fn map_quality<R, W, F>(reader: R, mut writer: W, mut f: F) -> Result<(), ParseError>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(u8) -> u8,
+{
+    let (mut reader, mut start_data) = decompress(reader)?;
+    if start_data.iter().all(u8::is_ascii_whitespace) && is_blank(&mut reader, &mut start_data)? {
+        return Ok(());
+    }
+    if detect_file_type(&start_data)? == "FASTA" {
+        writer.write_all(&start_data)?;
+        io::copy(&mut reader, &mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    let reader = SequenceReader::new(io::Cursor::new(start_data).chain(reader))?;
+    let format = SequenceFormat::from_file_type(reader.file_type());
+    let mut writer = SequenceWriter::new(writer, format);
+    for rec in reader {
+        let mut rec = rec?;
+        if let Some(qual) = rec.qual.as_mut() {
+            for q in qual.to_mut().iter_mut() {
+                *q = f(*q);
+            }
+        }
+        writer.write(&rec)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{dedup_by_id, map_quality, parse_and_tee, SequenceFormat, SequenceWriter};
+    use crate::sequence_record::SequenceRecord;
+    #[cfg(feature = "compression")]
+    use crate::util::TestDir;
+
+    #[test]
+    fn test_writer() {
+        let mut out = Vec::new();
+        let mut writer = SequenceWriter::new(&mut out, SequenceFormat::Fasta);
+        writer
+            .write(&SequenceRecord::new_owned("a", "ACGT", None))
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(&out[..], b">a\nACGT\n");
+    }
+
+    #[test]
+    fn test_repeat_id_on_plus() {
+        use crate::formats::parse_sequence_reader_with_options;
+        use crate::formats::ParseOptions;
+
+        let recs = vec![
+            SequenceRecord::new_owned("a 1", "ACGT", Some(b"IIII".to_vec())),
+            SequenceRecord::new_owned("b", "G", Some(b"#".to_vec())),
+        ];
+        let mut outputs = Vec::new();
+        for repeat in &[false, true] {
+            let mut out = Vec::new();
+            let mut writer =
+                SequenceWriter::new(&mut out, SequenceFormat::Fastq).repeat_id_on_plus(*repeat);
+            for rec in &recs {
+                writer.write(rec).unwrap();
+            }
+            writer.finish().unwrap();
+            outputs.push(out);
+        }
+        assert_eq!(&outputs[0][..], b"@a 1\nACGT\n+\nIIII\n@b\nG\n+\n#\n");
+        assert_eq!(&outputs[1][..], b"@a 1\nACGT\n+a 1\nIIII\n@b\nG\n+b\n#\n");
+
+        // both read back the same (even strictly)
+        let options = ParseOptions::new().strict_fastq(true);
+        for out in &outputs {
+            let mut parsed = Vec::new();
+            parse_sequence_reader_with_options(
+                &out[..],
+                &options,
+                |_| {},
+                |rec| parsed.push(rec.into_owned()),
+            )
+            .unwrap();
+            assert_eq!(parsed, recs);
+        }
+    }
+
+    #[test]
+    fn test_parse_and_tee() {
+        use std::io::Write;
+
+        let data = b"@a\nACGT\n+\nIIII\n@b\nGG\n+\n#!\n";
+        let (mut out1, mut out2) = (Vec::new(), Vec::new());
+        let mut ids = Vec::new();
+        let writers: Vec<Box<dyn Write>> = vec![Box::new(&mut out1), Box::new(&mut out2)];
+        parse_and_tee(&data[..], writers, |rec| ids.push(rec.id.into_owned())).unwrap();
+        assert_eq!(ids, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(&out1[..], &data[..]);
+        assert_eq!(out1, out2);
+    }
+
+    #[test]
+    fn test_dedup_by_id() {
+        let data = b">a first\nACGT\n>b\nGG\n>a second\nTTTT\n>c\nA\n>b\nCC\n";
+        let mut out = Vec::new();
+        assert_eq!(dedup_by_id(&data[..], &mut out), Ok(2));
+        assert_eq!(&out[..], b">a first\nACGT\n>b\nGG\n>c\nA\n");
+
+        let data = b"@a\nA\n+\nI\n@a\nG\n+\n#\n";
+        let mut out = Vec::new();
+        assert_eq!(dedup_by_id(&data[..], &mut out), Ok(1));
+        assert_eq!(&out[..], b"@a\nA\n+\nI\n");
+    }
+
+    #[test]
+    fn test_map_quality() {
+        // clamp the scores to between Phred 10 and 30
+        let clamp = |q: u8| q.clamp(b'+', b'?');
+        let data = b"@a\nACGTA\n+\n!+5?I\n@b desc\nGG\n+\nII\n";
+        let mut out = Vec::new();
+        map_quality(&data[..], &mut out, clamp).unwrap();
+        let mut quals = Vec::new();
+        crate::formats::parse_sequence_reader(
+            &out[..],
+            |_| {},
+            |rec| {
+                quals.push(rec.qual.unwrap().to_vec());
+            },
+        )
+        .unwrap();
+        assert_eq!(quals, [b"++5??".to_vec(), b"??".to_vec()]);
+
+        // FASTA has no qualities, so it's copied as is (wrapping and all)
+        let data = b">a\nACGT\n>b desc\r\nACGT\r\nAC\r\nG\n\n>c\n";
+        let mut out = Vec::new();
+        map_quality(&data[..], &mut out, |_| panic!("No qualities")).unwrap();
+        assert_eq!(&out[..], &data[..]);
+
+        let mut out = Vec::new();
+        map_quality(&b""[..], &mut out, |q| q).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_write_compressed() {
+        use super::write_compressed;
+        use crate::formats::parse_sequence_path;
+        use std::fs;
+
+        let dir = TestDir::new("write");
+        let recs = vec![
+            SequenceRecord::new_owned("a", "ACGT", Some(b"IIII".to_vec())),
+            SequenceRecord::new_owned("b", "GGC", Some(b"#!I".to_vec())),
+        ];
+
+        for name in &["test.fq.gz", "test.fq.bz2", "test.fq.xz", "test.fq"] {
+            let path = dir.join(name);
+            let mut writer = write_compressed(&path, SequenceFormat::Fastq, 6).unwrap();
+            for rec in &recs {
+                writer.write(rec).unwrap();
+            }
+            writer.finish().unwrap();
+
+            let raw = fs::read(&path).unwrap();
+            if name.ends_with(".gz") {
+                assert_eq!(&raw[..2], &[0x1F, 0x8B]);
+            } else if name.ends_with(".fq") {
+                assert_eq!(raw[0], b'@');
+            }
+
+            let mut i = 0;
+            parse_sequence_path(
+                &path,
+                |file_type| assert_eq!(file_type, "FASTQ"),
+                |rec| {
+                    assert_eq!(rec.id, recs[i].id);
+                    assert_eq!(rec.seq, recs[i].seq);
+                    assert_eq!(rec.qual, recs[i].qual);
+                    i += 1;
+                },
+            )
+            .unwrap();
+            assert_eq!(i, 2);
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_rsyncable() {
+        use super::write_compressed;
+        use flate2::bufread::GzDecoder;
+        use std::fs;
+        use std::io::Read;
+
+        // the decompressed contents of each gzip member
+        let members = |data: &[u8]| {
+            let mut members = Vec::new();
+            let mut rest = data;
+            while !rest.is_empty() {
+                let mut decoder = GzDecoder::new(rest);
+                let mut member = Vec::new();
+                decoder.read_to_end(&mut member).unwrap();
+                members.push(member);
+                rest = decoder.into_inner();
+            }
+            members
+        };
+
+        let dir = TestDir::new("rsync");
+        let path = dir.join("test.fa.gz");
+        let write = |recs: &[SequenceRecord], rsyncable: bool| {
+            let writer = write_compressed(&path, SequenceFormat::Fasta, 6).unwrap();
+            let mut writer = writer.rsyncable(rsyncable);
+            for rec in recs {
+                writer.write(rec).unwrap();
+            }
+            writer.finish().unwrap();
+            fs::read(&path).unwrap()
+        };
+
+        let mut recs: Vec<_> = (0..1000)
+            .map(|i| SequenceRecord::new_owned(format!("read{}", i), "ACGTTGCA", None))
+            .collect();
+        let plain = write(&recs, false);
+        assert_eq!(members(&plain).len(), 1);
+        let rsyncable = write(&recs, true);
+        let original = members(&rsyncable);
+        assert!(original.len() > 5);
+        // every member is whole records
+        for member in &original {
+            assert_eq!(member[0], b'>');
+            assert_eq!(member.last(), Some(&b'\n'));
+        }
+        assert_eq!(original.concat(), members(&plain).concat());
+
+        // and it reads back like any other gzip file
+        let mut n_records = 0;
+        crate::formats::parse_sequence_path(&path, |_| {}, |_| n_records += 1).unwrap();
+        assert_eq!(n_records, 1000);
+
+        // changing one record only changes the member it's in
+        recs[500] = SequenceRecord::new_owned("read500", "GGGG", None);
+        let changed = members(&write(&recs, true));
+        assert_eq!(changed.len(), original.len());
+        let n_different = changed
+            .iter()
+            .zip(&original)
+            .filter(|(a, b)| a != b)
+            .count();
+        assert_eq!(n_different, 1);
+    }
+}
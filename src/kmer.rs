@@ -1,5 +1,9 @@
 //! Functions for splitting sequences into fixed-width moving windows (kmers)
 //! and utilities for dealing with these kmers.
+use std::borrow::Cow;
+
+use crate::sequence::canonical;
+use crate::util::fnv1a_64;
 
 /// Returns true if the base is a unambiguous nucleic acid base (e.g. ACGT) and
 /// false otherwise.
@@ -10,6 +14,29 @@ fn is_good_base(chr: u8) -> bool {
     }
 }
 
+/// A hash of the canonical form of a (nucleic acid) kmer that's stable
+/// across platforms and needletail versions, so it can be used in on-disk
+/// kmer databases. The kmer is uppercased and canonicalized (see
+/// `sequence::canonical`) so a kmer and its reverse complement hash the
+/// same, and then hashed with 64-bit FNV-1a.
+///
+/// This function's output is part of the public API: changing it for any
+/// input requires a major version bump.
+///
+/// ```
+/// use needletail::kmer::canonical_kmer_hash;
+///
+/// assert_eq!(canonical_kmer_hash(b"ACGT"), 11137427767090403918);
+/// assert_eq!(canonical_kmer_hash(b"AAAA"), 9890857558446683929);
+/// assert_eq!(canonical_kmer_hash(b"TTTT"), 9890857558446683929);
+/// assert_eq!(canonical_kmer_hash(b"acTT"), 9885244551585802124);
+/// assert_eq!(canonical_kmer_hash(b"GATTACA"), 10139454686114222884);
+/// ```
+pub fn canonical_kmer_hash(kmer: &[u8]) -> u64 {
+    let kmer = kmer.to_ascii_uppercase();
+    fnv1a_64(&canonical(&kmer))
+}
+
 /// Generic moving window iterator over sequences to return k-mers
 ///
 /// Iterator returns slices to the original data.
@@ -43,6 +70,55 @@ impl<'a> Iterator for Kmers<'a> {
     }
 }
 
+/// A moving window iterator over a circular sequence (e.g. a plasmid or a
+/// mitochondrial genome) that wraps around from the end to the start, so
+/// the k-mers spanning the origin are included too. There's one k-mer for
+/// every position in the sequence; the ones that wrap around are copied and
+/// the rest are slices of the original data. Nothing is returned if `k` is
+/// 0 or longer than the sequence.
+///
+/// ```
+/// use needletail::Sequence;
+///
+/// let kmers: Vec<_> = b"ACGT".circular_kmers(3).collect();
+/// assert_eq!(kmers, vec![&b"ACG"[..], b"CGT", b"GTA", b"TAC"]);
+/// ```
+pub struct CircularKmers<'a> {
+    k: u8,
+    start_pos: usize,
+    buffer: &'a [u8],
+}
+
+impl<'a> CircularKmers<'a> {
+    pub fn new(buffer: &'a [u8], k: u8) -> Self {
+        CircularKmers {
+            k,
+            start_pos: 0,
+            buffer,
+        }
+    }
+}
+
+impl<'a> Iterator for CircularKmers<'a> {
+    type Item = Cow<'a, [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let k = self.k as usize;
+        if k == 0 || k > self.buffer.len() || self.start_pos >= self.buffer.len() {
+            return None;
+        }
+        let pos = self.start_pos;
+        self.start_pos += 1;
+        if pos + k <= self.buffer.len() {
+            Some(Cow::Borrowed(&self.buffer[pos..pos + k]))
+        } else {
+            let mut kmer = self.buffer[pos..].to_vec();
+            kmer.extend_from_slice(&self.buffer[..k - kmer.len()]);
+            Some(Cow::Owned(kmer))
+        }
+    }
+}
+
 /// A kmer-izer for a nucleotide acid sequences to return canonical kmers.
 ///
 /// Iterator returns the position of the kmer, a slice to the original data,
@@ -51,11 +51,20 @@
 pub mod bitkmer;
 pub mod formats;
 pub mod kmer;
+pub mod matcher;
+pub mod quality;
 pub mod sequence;
 pub mod sequence_record;
+pub mod stats;
+pub mod translate;
+pub mod trim;
 mod util;
 
-pub use formats::{parse_sequence_path, parse_sequence_reader};
+pub use formats::{
+    parse_many, parse_paired, parse_sequence_bufread, parse_sequence_glob, parse_sequence_path,
+    parse_sequence_reader, parse_sequence_reader_lenient, parse_sequence_reader_with_options,
+    ParseOptions,
+};
 pub use sequence::Sequence;
-pub use sequence_record::SequenceRecord;
+pub use sequence_record::{OwnedSequenceRecord, SequenceRecord, SequenceRecordBuilder};
 pub use util::{ParseError, ParseErrorType};
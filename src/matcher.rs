@@ -0,0 +1,106 @@
+//! Matching reads against known sequences (e.g. spike-ins or barcodes).
+use crate::sequence::Sequence;
+
+/// A set of reference sequences that reads can be matched against.
+///
+/// Reads are compared to each reference by Hamming distance over the
+/// reference's length (so a read has to be at least as long as a reference
+/// to match it), ignoring case.
+///
+/// ```
+/// use needletail::matcher::ReferenceMatcher;
+///
+/// let mut matcher = ReferenceMatcher::new(1);
+/// matcher.add("phix", "GAGTTTTATCGC");
+/// matcher.add("lambda", "GGGCGGCGACCT");
+/// assert_eq!(matcher.best_match(b"GAGTTTTATGGCTTA"), Some((&b"phix"[..], 1)));
+/// assert_eq!(matcher.best_match(b"CCCCCCCCCCCCCCC"), None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ReferenceMatcher {
+    references: Vec<(Vec<u8>, Vec<u8>)>,
+    max_mismatches: usize,
+}
+
+impl ReferenceMatcher {
+    /// Create an empty matcher that allows up to `max_mismatches` mismatches
+    pub fn new(max_mismatches: usize) -> Self {
+        ReferenceMatcher {
+            references: Vec::new(),
+            max_mismatches,
+        }
+    }
+
+    /// Add a reference sequence with a name
+    pub fn add<N, S>(&mut self, name: N, seq: S)
+    where
+        N: Into<Vec<u8>>,
+        S: Into<Vec<u8>>,
+    {
+        self.references.push((name.into(), seq.into()));
+    }
+
+    /// The number of references in the matcher
+    pub fn len(&self) -> usize {
+        self.references.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.references.is_empty()
+    }
+
+    /// Find the reference closest to the start of `read`. Returns its name
+    /// and how many mismatches there were, or `None` if no reference is
+    /// within the mismatch budget. Ties go to the reference added first.
+    pub fn best_match(&self, read: &[u8]) -> Option<(&[u8], usize)> {
+        let mut best: Option<(&[u8], usize)> = None;
+        for (name, seq) in &self.references {
+            if seq.len() > read.len() {
+                continue;
+            }
+            let mismatches = seq.count_differences_to(read);
+            match best {
+                Some((_, m)) if m <= mismatches => {}
+                _ if mismatches > self.max_mismatches => {}
+                _ => best = Some((name, mismatches)),
+            }
+            if mismatches == 0 {
+                break;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReferenceMatcher;
+
+    #[test]
+    fn test_best_match() {
+        let mut matcher = ReferenceMatcher::new(2);
+        matcher.add("ref1", "ACGTACGTAC");
+        matcher.add("ref2", "TTTTGGGGCC");
+        matcher.add("ref3", "ACGTACGTTT");
+        assert_eq!(matcher.len(), 3);
+
+        // one mismatch from ref3 and two from ref1
+        let read = b"ACGTACGTTAGATTACA";
+        assert_eq!(matcher.best_match(read), Some((&b"ref3"[..], 1)));
+        assert_eq!(
+            matcher.best_match(b"ttttggggccAAA"),
+            Some((&b"ref2"[..], 0))
+        );
+
+        // too many mismatches from everything
+        assert_eq!(matcher.best_match(b"GGGGGGGGGGGG"), None);
+        // and too short to compare
+        assert_eq!(matcher.best_match(b"ACGTACGT"), None);
+
+        // ties go to the first reference
+        let mut matcher = ReferenceMatcher::new(1);
+        matcher.add("a", "AAAA");
+        matcher.add("b", "AAAT");
+        assert_eq!(matcher.best_match(b"AAAG"), Some((&b"a"[..], 1)));
+    }
+}
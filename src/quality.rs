@@ -0,0 +1,234 @@
+//! Transformations of (Phred+33 encoded) FASTQ quality scores.
+
+/// The offset of Phred scores in FASTQ quality lines
+const PHRED_OFFSET: u8 = 33;
+
+/// The highest score that can be written in a FASTQ file (`~` with a
+/// Phred+33 offset)
+const MAX_SCORE: u8 = 93;
+
+/// A scheme for binning quality scores into a smaller number of levels (to
+/// make them compress better); every quality byte is mapped to the
+/// representative score of the bin it falls into.
+#[derive(Clone)]
+pub struct QualityBinScheme {
+    table: [u8; 256],
+}
+
+impl QualityBinScheme {
+    /// Build a binning scheme from a list of `(lowest score, representative
+    /// score)` pairs (as Phred scores, not ASCII), sorted by their lowest
+    /// score. Each bin runs up to the start of the next one and the last bin
+    /// catches everything above it. Scores below the first bin (and any
+    /// non-quality bytes) are left alone.
+    ///
+    /// # Panics
+    ///
+    /// If the bins aren't sorted or a representative score can't be
+    /// encoded.
+    pub fn new(bins: &[(u8, u8)]) -> Self {
+        assert!(
+            bins.windows(2).all(|w| w[0].0 < w[1].0),
+            "Quality bins must be sorted by their lowest score"
+        );
+        let mut table = [0; 256];
+        for (byte, binned) in table.iter_mut().enumerate() {
+            let byte = byte as u8;
+            *binned = byte;
+            if byte < PHRED_OFFSET {
+                continue;
+            }
+            let score = byte - PHRED_OFFSET;
+            if let Some((_, repr)) = bins.iter().rev().find(|(low, _)| *low <= score) {
+                *binned = repr
+                    .checked_add(PHRED_OFFSET)
+                    .expect("Representative quality score is too large");
+            }
+        }
+        QualityBinScheme { table }
+    }
+
+    /// The 8-level binning Illumina uses on its newer instruments (scores of
+    /// 0 and 1, i.e. no-calls, are left as is)
+    pub fn illumina_8_level() -> Self {
+        QualityBinScheme::new(&[
+            (2, 6),
+            (10, 15),
+            (20, 22),
+            (25, 27),
+            (30, 33),
+            (35, 37),
+            (40, 40),
+        ])
+    }
+
+    /// Bin a single quality byte
+    #[inline]
+    pub fn bin(&self, qual: u8) -> u8 {
+        self.table[qual as usize]
+    }
+}
+
+/// The Phred+33 byte for a quality score, with scores above 93 written as
+/// 93 (the highest one a FASTQ file can hold)
+#[inline]
+pub(crate) fn phred33(score: u8) -> u8 {
+    score.min(MAX_SCORE) + PHRED_OFFSET
+}
+
+/// The number of different scores `PerPositionQuality` counts
+const N_SCORES: usize = MAX_SCORE as usize + 1;
+
+/// The distribution of quality scores at each position of a set of reads
+/// (e.g. for FastQC-style per-base quality plots).
+///
+/// ```
+/// use needletail::quality::PerPositionQuality;
+///
+/// let mut quals = PerPositionQuality::new(150);
+/// quals.add(b"II5", 33);
+/// quals.add(b"5", 33);
+/// assert_eq!(quals.mean(0), 30.);
+/// assert_eq!(quals.median(1), Some(40));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerPositionQuality {
+    counts: Vec<[u64; N_SCORES]>,
+}
+
+impl PerPositionQuality {
+    /// Create an empty distribution with room for reads up to `max_len`
+    /// long (longer reads extend it as they're added)
+    pub fn new(max_len: usize) -> Self {
+        PerPositionQuality {
+            counts: vec![[0; N_SCORES]; max_len],
+        }
+    }
+
+    /// The number of positions in the distribution
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Add the quality scores of a read, encoded with the given offset
+    /// (e.g. 33 for Phred+33). Scores that would be below 0 are counted as
+    /// 0 and ones above 93 as 93.
+    pub fn add(&mut self, qual: &[u8], offset: u8) {
+        if qual.len() > self.counts.len() {
+            self.counts.resize(qual.len(), [0; N_SCORES]);
+        }
+        for (counts, q) in self.counts.iter_mut().zip(qual) {
+            let score = q.saturating_sub(offset).min(MAX_SCORE);
+            counts[usize::from(score)] += 1;
+        }
+    }
+
+    /// The number of reads that had a score at a position
+    pub fn n_reads(&self, pos: usize) -> u64 {
+        self.counts.get(pos).map_or(0, |c| c.iter().sum())
+    }
+
+    /// The mean score at a position (NaN if no reads reached it)
+    pub fn mean(&self, pos: usize) -> f64 {
+        let counts = match self.counts.get(pos) {
+            Some(c) => c,
+            None => return f64::NAN,
+        };
+        let total: u64 = counts
+            .iter()
+            .enumerate()
+            .map(|(score, n)| score as u64 * n)
+            .sum();
+        total as f64 / self.n_reads(pos) as f64
+    }
+
+    /// The score at a fraction of the way through a position's sorted
+    /// scores (using the nearest rank)
+    fn percentile(&self, pos: usize, fraction: f64) -> Option<u8> {
+        let n_reads = self.n_reads(pos);
+        if n_reads == 0 {
+            return None;
+        }
+        let rank = ((fraction * n_reads as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (score, n) in self.counts[pos].iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Some(score as u8);
+            }
+        }
+        None
+    }
+
+    /// The median score at a position (`None` if no reads reached it)
+    pub fn median(&self, pos: usize) -> Option<u8> {
+        self.percentile(pos, 0.5)
+    }
+
+    /// The lower quartile, median and upper quartile scores at a position
+    /// (`None` if no reads reached it)
+    pub fn quartiles(&self, pos: usize) -> Option<(u8, u8, u8)> {
+        Some((
+            self.percentile(pos, 0.25)?,
+            self.percentile(pos, 0.5)?,
+            self.percentile(pos, 0.75)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PerPositionQuality, QualityBinScheme};
+    use crate::sequence::QualitySequence;
+
+    #[test]
+    fn test_illumina_binning() {
+        let scheme = QualityBinScheme::illumina_8_level();
+        let scores: Vec<u8> = (0..=45).collect();
+        let binned: Vec<u8> = scores.iter().map(|q| scheme.bin(q + 33) - 33).collect();
+        let mut expected = vec![0, 1];
+        expected.extend(vec![6; 8]);
+        expected.extend(vec![15; 10]);
+        expected.extend(vec![22; 5]);
+        expected.extend(vec![27; 5]);
+        expected.extend(vec![33; 5]);
+        expected.extend(vec![37; 5]);
+        expected.extend(vec![40; 6]);
+        assert_eq!(binned, expected);
+
+        let seq = (&b"ACGTN"[..], &b"I5+&!"[..]);
+        assert_eq!(&seq.bin_quality(&scheme)[..], b"I70'!");
+    }
+
+    #[test]
+    fn test_per_position_quality() {
+        let mut quals = PerPositionQuality::new(2);
+        quals.add(b"I5+", 33);
+        quals.add(b"5+", 33);
+        quals.add(b"+!", 33);
+        quals.add(b"5", 33);
+        // the longer read extended the positions
+        assert_eq!(quals.len(), 3);
+
+        // 40, 20, 10 and 20
+        assert_eq!(quals.n_reads(0), 4);
+        assert_eq!(quals.mean(0), 22.5);
+        assert_eq!(quals.median(0), Some(20));
+        assert_eq!(quals.quartiles(0), Some((10, 20, 20)));
+        assert_eq!(quals.quartiles(2), Some((10, 10, 10)));
+
+        assert!(quals.mean(3).is_nan());
+        assert_eq!(quals.median(3), None);
+
+        // other offsets and out of range scores
+        let mut quals = PerPositionQuality::new(1);
+        quals.add(&[64 + 30, 10, 255], 64);
+        assert_eq!(quals.median(0), Some(30));
+        assert_eq!(quals.median(1), Some(0));
+        assert_eq!(quals.median(2), Some(93));
+    }
+}
@@ -1,10 +1,16 @@
 //! Generic functions for working with (primarily nucleic acid) sequences
 use std::borrow::Cow;
+use std::iter::{Map, Rev};
+use std::slice::{ChunksExact, Iter};
 
-use memchr::memchr2;
+use memchr::{memchr, memchr2};
 
 use crate::bitkmer::BitNuclKmer;
-use crate::kmer::{CanonicalKmers, Kmers};
+use crate::kmer::{CanonicalKmers, CircularKmers, Kmers};
+use crate::quality::QualityBinScheme;
+use crate::translate::{longest_forward_orf, CodonTable, Orf};
+use crate::trim::{quality_trim, TrimAnnotation, TrimOptions, TrimReason};
+use crate::util::{fnv1a_128, SplitMix64};
 
 /// Transform a nucleic acid sequence into its "normalized" form.
 ///
@@ -92,6 +98,44 @@ fn test_normalize() {
     );
 }
 
+/// The (uppercase) bases allowed in a DNA sequence, for use with
+/// `Sequence::find_invalid`.
+pub const DNA_ALPHABET: &[u8] = b"ACGTN";
+
+/// The (uppercase) residues allowed in a protein sequence, for use with
+/// `Sequence::find_invalid`: the 20 standard amino acids, the ambiguity
+/// codes `B`, `Z`, `J` and `X`, selenocysteine (`U`), pyrrolysine (`O`), stop
+/// codons (`*`) and gaps (`-`).
+pub const PROTEIN_ALPHABET: &[u8] = b"ACDEFGHIKLMNPQRSTVWYBZJXUO*-";
+
+/// The bytes that `Sequence::strip_whitespace` removes: spaces, tabs, line
+/// feeds, vertical tabs, form feeds and carriage returns.
+pub const WHITESPACE: &[u8] = b" \t\n\x0b\x0c\r";
+
+/// Returns true for the bytes in `WHITESPACE`.
+#[inline]
+fn is_whitespace(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+/// Swap every `from` base in `seq` for `to` (matching and preserving case);
+/// only allocates if there's something to swap. `from` and `to` should be
+/// uppercase.
+fn replace_base(seq: &[u8], from: u8, to: u8) -> Cow<'_, [u8]> {
+    let lower = from.to_ascii_lowercase();
+    if memchr2(from, lower, seq).is_none() {
+        return seq.into();
+    }
+    seq.iter()
+        .map(|&c| match c {
+            c if c == from => to,
+            c if c == lower => to.to_ascii_lowercase(),
+            c => c,
+        })
+        .collect::<Vec<u8>>()
+        .into()
+}
+
 /// Returns the complementary base for a given IUPAC base code.
 ///
 /// Does not work for RNA sequences (maybe we should raise an error or something?)
@@ -135,6 +179,21 @@ pub fn complement(n: u8) -> u8 {
     }
 }
 
+/// Returns true if two RNA bases can pair with each other, either as a
+/// Watson-Crick pair (A-U, G-C) or a G-U wobble pair. Case is ignored and T
+/// is treated the same as U.
+#[inline]
+pub fn can_pair(a: u8, b: u8) -> bool {
+    let norm = |n: u8| match n.to_ascii_uppercase() {
+        b'T' => b'U',
+        n => n,
+    };
+    matches!(
+        (norm(a), norm(b)),
+        (b'A', b'U') | (b'U', b'A') | (b'G', b'C') | (b'C', b'G') | (b'G', b'U') | (b'U', b'G')
+    )
+}
+
 /// Taking in a sequence string, return the canonical form of the sequence
 /// (e.g. the lexigraphically lowest of either the original sequence or its
 /// reverse complement)
@@ -182,6 +241,211 @@ pub fn minimizer(seq: &[u8], length: usize) -> Cow<[u8]> {
     minmer
 }
 
+/// Calculate the GC skew, `(G - C) / (G + C)`, of a sequence (ignoring
+/// case). Returns NaN if the sequence has no G or C bases.
+pub fn gc_skew(seq: &[u8]) -> f64 {
+    let (mut g, mut c) = (0usize, 0usize);
+    for n in seq {
+        match n {
+            b'G' | b'g' => g += 1,
+            b'C' | b'c' => c += 1,
+            _ => {}
+        }
+    }
+    if g + c == 0 {
+        return f64::NAN;
+    }
+    (g as f64 - c as f64) / (g + c) as f64
+}
+
+/// The fraction of the A, C, G and T (or U) bases in a sequence that are G
+/// or C (ignoring case); NaN if there aren't any
+fn gc_fraction(seq: &[u8]) -> f64 {
+    let (mut gc, mut total) = (0usize, 0usize);
+    for base in seq {
+        match base {
+            b'G' | b'g' | b'C' | b'c' => {
+                gc += 1;
+                total += 1;
+            }
+            b'A' | b'a' | b'T' | b't' | b'U' | b'u' => total += 1,
+            _ => {}
+        }
+    }
+    if total == 0 {
+        return f64::NAN;
+    }
+    gc as f64 / total as f64
+}
+
+/// Wrap a sequence into lines of `width` bytes (with `\n` between the
+/// lines but not after the last one), e.g. for writing FASTA files with a
+/// fixed line length. A `width` of 0 leaves the sequence on one line.
+///
+/// ```
+/// use needletail::sequence::wrap_sequence;
+///
+/// assert_eq!(wrap_sequence(b"ACGTACG", 3), b"ACG\nTAC\nG");
+/// ```
+pub fn wrap_sequence(seq: &[u8], width: usize) -> Vec<u8> {
+    if width == 0 {
+        return seq.to_vec();
+    }
+    let mut wrapped = Vec::with_capacity(seq.len() + seq.len() / width);
+    for (i, line) in seq.chunks(width).enumerate() {
+        if i > 0 {
+            wrapped.push(b'\n');
+        }
+        wrapped.extend_from_slice(line);
+    }
+    wrapped
+}
+
+/// The bases (as a bitmask with A as the lowest bit, then C, G and T) that
+/// an IUPAC code stands for
+#[inline]
+fn iupac_mask(code: u8) -> u8 {
+    match code.to_ascii_uppercase() {
+        b'A' => 0b0001,
+        b'C' => 0b0010,
+        b'G' => 0b0100,
+        b'T' | b'U' => 0b1000,
+        b'M' => 0b0011,
+        b'R' => 0b0101,
+        b'W' => 0b1001,
+        b'S' => 0b0110,
+        b'Y' => 0b1010,
+        b'K' => 0b1100,
+        b'V' => 0b0111,
+        b'H' => 0b1011,
+        b'D' => 0b1101,
+        b'B' => 0b1110,
+        b'N' => 0b1111,
+        _ => 0,
+    }
+}
+
+/// Returns true if `base` is one of the bases the IUPAC code `code` stands
+/// for (ignoring case and treating U as T), e.g. `R` matches `A` and `G`.
+/// An ambiguous base only matches codes that cover all of its bases.
+pub fn iupac_match(code: u8, base: u8) -> bool {
+    let base = iupac_mask(base);
+    base != 0 && iupac_mask(code) & base == base
+}
+
+/// The class of a base for `base_entropy` (A, C, G, T/U or anything else)
+#[inline]
+fn entropy_class(base: u8) -> usize {
+    match base {
+        b'A' | b'a' => 0,
+        b'C' | b'c' => 1,
+        b'G' | b'g' => 2,
+        b'T' | b't' | b'U' | b'u' => 3,
+        _ => 4,
+    }
+}
+
+fn entropy_from_counts(counts: &[usize; 5], total: usize) -> f64 {
+    counts
+        .iter()
+        .filter(|n| **n > 0)
+        .map(|n| {
+            let p = *n as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Calculate the Shannon entropy (in bits) of the bases in a sequence,
+/// ignoring case and counting every non-ACGT character together. This is
+/// 2 for a sequence with equal amounts of A, C, G and T and 0 for a
+/// homopolymer. Returns NaN for an empty sequence.
+pub fn base_entropy(seq: &[u8]) -> f64 {
+    if seq.is_empty() {
+        return f64::NAN;
+    }
+    let mut counts = [0; 5];
+    for base in seq {
+        counts[entropy_class(*base)] += 1;
+    }
+    entropy_from_counts(&counts, seq.len())
+}
+
+/// How `Sequence::mask_low_complexity` should mask bases
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaskMode {
+    /// Lowercase the bases
+    Soft,
+    /// Replace the bases with `N`s
+    Hard,
+}
+
+/// How much of a sequence is masked; see `Sequence::masking_stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MaskStats {
+    /// Lowercase (soft-masked) bases
+    pub soft: usize,
+    /// `N`s (hard-masked bases) in either case
+    pub hard: usize,
+    /// Uppercase bases other than `N`
+    pub unmasked: usize,
+}
+
+/// An iterator over the reverse complement of a sequence; see
+/// `Sequence::rev_comp_iter`.
+pub type RevCompIter<'a> = Map<Rev<Iter<'a, u8>>, fn(&u8) -> u8>;
+
+/// An iterator over the pieces of a sequence between gaps (runs of `N`s);
+/// see `Sequence::split_at_gaps`.
+pub struct GapSplits<'a> {
+    seq: &'a [u8],
+    pos: usize,
+    min_gap: usize,
+}
+
+impl<'a> GapSplits<'a> {
+    pub fn new(seq: &'a [u8], min_gap: usize) -> Self {
+        assert!(min_gap > 0, "min_gap must be at least 1");
+        GapSplits {
+            seq,
+            pos: 0,
+            min_gap,
+        }
+    }
+}
+
+impl<'a> Iterator for GapSplits<'a> {
+    type Item = (usize, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let is_n = |c: &u8| *c == b'N' || *c == b'n';
+        while self.pos < self.seq.len() {
+            let start = self.pos;
+            // find the next run of Ns that's long enough to split at
+            let mut end = start;
+            let mut gap_end = self.seq.len();
+            while end < self.seq.len() {
+                if is_n(&self.seq[end]) {
+                    let run = self.seq[end..].iter().take_while(|c| is_n(c)).count();
+                    if run >= self.min_gap {
+                        gap_end = end + run;
+                        break;
+                    }
+                    end += run;
+                } else {
+                    end += 1;
+                }
+            }
+            self.pos = gap_end;
+            // a gap at the very start doesn't have anything before it
+            if end > start {
+                return Some((start, &self.seq[start..end]));
+            }
+        }
+        None
+    }
+}
+
 /// A generic FASTX record that also abstracts over several logical operations
 /// that can be performed on nucleic acid sequences.
 pub trait Sequence<'a> {
@@ -220,6 +484,65 @@ pub trait Sequence<'a> {
         new_buf.into()
     }
 
+    /// The first `n` bases of the sequence (or the whole sequence if it's
+    /// shorter than that).
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGTA".head(3), b"ACG");
+    /// assert_eq!(b"ACGTA".head(10), b"ACGTA");
+    /// ```
+    fn head(&'a self, n: usize) -> &'a [u8] {
+        let seq = self.sequence();
+        &seq[..n.min(seq.len())]
+    }
+
+    /// The last `n` bases of the sequence (or the whole sequence if it's
+    /// shorter than that).
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGTA".tail(3), b"GTA");
+    /// ```
+    fn tail(&'a self, n: usize) -> &'a [u8] {
+        let seq = self.sequence();
+        &seq[seq.len() - n.min(seq.len())..]
+    }
+
+    /// Remove all whitespace (every byte in `WHITESPACE`, i.e. spaces,
+    /// tabs, line endings, vertical tabs and form feeds) from the sequence.
+    /// Like `strip_returns` this only allocates if there's something to
+    /// remove.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"AC GT\r\nA\tC".strip_whitespace().as_ref(), b"ACGTAC");
+    /// ```
+    fn strip_whitespace(&'a self) -> Cow<'a, [u8]> {
+        self.strip_whitespace_keeping(b"")
+    }
+
+    /// Remove whitespace from the sequence like `strip_whitespace`, but
+    /// retain any of the bytes in `keep` (e.g. `b" "` to only remove line
+    /// endings). Bytes that aren't whitespace are always kept.
+    fn strip_whitespace_keeping(&'a self, keep: &[u8]) -> Cow<'a, [u8]> {
+        let seq = self.sequence();
+        let remove = |c: &u8| is_whitespace(*c) && !keep.contains(c);
+
+        // only allocate a new buffer if there's something to strip out
+        let i = match seq.iter().position(remove) {
+            Some(i) => i,
+            None => return seq.into(),
+        };
+        let mut new_buf = Vec::with_capacity(seq.len() - 1);
+        new_buf.extend_from_slice(&seq[..i]);
+        new_buf.extend(seq[i..].iter().filter(|c| !remove(c)));
+        new_buf.into()
+    }
+
     /// Returns the reverse complement of a sequence. Biologically this is
     /// equivalent to the sequence of the strand opposite the one you pass
     /// in.
@@ -230,11 +553,60 @@ pub trait Sequence<'a> {
     /// assert_eq!(b"AACC".reverse_complement(), b"GGTT");
     /// ```
     fn reverse_complement(&'a self) -> Vec<u8> {
-        self.sequence()
-            .iter()
-            .rev()
-            .map(|n| complement(*n))
-            .collect()
+        self.rev_comp_iter().collect()
+    }
+
+    /// Lazily iterate over the reverse complement of the sequence (from the
+    /// 3' end) without allocating a new buffer for it.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let rc: Vec<u8> = b"AACc".rev_comp_iter().collect();
+    /// assert_eq!(rc, b"gGTT");
+    /// ```
+    fn rev_comp_iter(&'a self) -> RevCompIter<'a> {
+        self.sequence().iter().rev().map(|n| complement(*n))
+    }
+
+    /// [Nucleic Acids] Returns whichever of the sequence or its reverse
+    /// complement is lexigraphically smaller, along with whether the reverse
+    /// complement was the one returned.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"TTGC".canonical(), (b"GCAA".to_vec(), true));
+    /// ```
+    fn canonical(&'a self) -> (Vec<u8>, bool) {
+        match canonical(self.sequence()) {
+            Cow::Borrowed(seq) => (seq.to_vec(), false),
+            Cow::Owned(rc) => (rc, true),
+        }
+    }
+
+    /// [Nucleic Acids] Transcribes a DNA sequence into RNA by converting
+    /// T's into U's (preserving case). Everything else is left alone.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGTt".to_rna().as_ref(), b"ACGUu");
+    /// ```
+    fn to_rna(&'a self) -> Cow<'a, [u8]> {
+        replace_base(self.sequence(), b'T', b'U')
+    }
+
+    /// [Nucleic Acids] Converts an RNA sequence into DNA by converting U's
+    /// into T's (preserving case). Everything else is left alone.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGUu".to_dna().as_ref(), b"ACGTt");
+    /// ```
+    fn to_dna(&'a self) -> Cow<'a, [u8]> {
+        replace_base(self.sequence(), b'U', b'T')
     }
 
     /// [Nucleic Acids] Normalizes the sequence. See documentation for
@@ -261,95 +633,976 @@ pub trait Sequence<'a> {
         }
     }
 
-    /// [Nucleic Acids] Returns an iterator over the sequence that skips
-    /// non-ACGT bases and returns a tuple containing (position, the
-    /// canonicalized kmer, if the sequence is the complement of the original).
-    fn canonical_kmers(&'a self, k: u8, reverse_complement: &'a [u8]) -> CanonicalKmers<'a> {
-        CanonicalKmers::new(self.sequence().as_ref(), reverse_complement, k)
+    /// Uppercase every ASCII letter in the sequence. Unlike `normalize`
+    /// nothing else is changed (U's stay U's, gaps and unknown characters
+    /// are kept, etc).
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"acgu-n".to_uppercase(), b"ACGU-N");
+    /// ```
+    fn to_uppercase(&'a self) -> Vec<u8> {
+        self.sequence().to_ascii_uppercase()
     }
 
-    /// Returns an iterator that returns a sliding window of k-sized
-    /// sequences (k-mers). Does not skip whitespace or correct bases in the
-    /// original sequence so `.normalize` or `.strip_returns` may be
-    /// appropriate to use first.
-    fn kmers(&'a self, k: u8) -> Kmers<'a> {
-        Kmers::new(self.sequence().as_ref(), k)
+    /// Lowercase every ASCII letter in the sequence (see `to_uppercase`).
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGU-N".to_lowercase(), b"acgu-n");
+    /// ```
+    fn to_lowercase(&'a self) -> Vec<u8> {
+        self.sequence().to_ascii_lowercase()
     }
 
-    /// Return an iterator that returns valid kmers in 4-bit form
-    fn bit_kmers(&'a self, k: u8, canonical: bool) -> BitNuclKmer<'a> {
-        BitNuclKmer::new(self.sequence(), k, canonical)
+    /// [Nucleic Acids] Returns true if the sequence can fold back on itself
+    /// into a perfect hairpin, i.e. every base can pair (see `can_pair`)
+    /// with the base the same distance from the other end. The middle base
+    /// of an odd-length sequence is left unpaired.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert!(b"GGAUCU".is_palindromic_rna());
+    /// assert!(!b"GGAACC".is_palindromic_rna());
+    /// ```
+    fn is_palindromic_rna(&'a self) -> bool {
+        let seq = self.sequence();
+        seq.iter()
+            .zip(seq.iter().rev())
+            .take(seq.len() / 2)
+            .all(|(a, b)| can_pair(*a, *b))
     }
-}
 
-impl<'a> Sequence<'a> for &'a [u8] {
-    fn sequence(&'a self) -> &'a [u8] {
-        &self
+    /// [Nucleic Acids] Split the sequence at gaps (runs of at least
+    /// `min_gap` `N`s, e.g. between the contigs of a scaffold), returning
+    /// the position and bases of every piece in between. Shorter runs of
+    /// `N`s are kept in the pieces.
+    ///
+    /// # Panics
+    ///
+    /// If `min_gap` is 0.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let pieces: Vec<_> = b"ACNNNNGT".split_at_gaps(3).collect();
+    /// assert_eq!(pieces, vec![(0, &b"AC"[..]), (6, &b"GT"[..])]);
+    /// ```
+    fn split_at_gaps(&'a self, min_gap: usize) -> GapSplits<'a> {
+        GapSplits::new(self.sequence(), min_gap)
     }
-}
 
-impl<'a> Sequence<'a> for [u8] {
-    fn sequence(&'a self) -> &'a [u8] {
-        &self
+    /// [Nucleic Acids] Homopolymer compress the sequence by collapsing
+    /// runs of the same base (byte) down to a single base. Returns the
+    /// compressed sequence and how long each run was, so the original
+    /// sequence can be rebuilt by repeating each base that many times.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let (hpc, runs) = b"AAACCG".homopolymer_compress();
+    /// assert_eq!(hpc, b"ACG");
+    /// assert_eq!(runs, vec![3, 2, 1]);
+    /// ```
+    fn homopolymer_compress(&'a self) -> (Vec<u8>, Vec<u32>) {
+        let mut compressed = Vec::new();
+        let mut runs: Vec<u32> = Vec::new();
+        for base in self.sequence() {
+            match (compressed.last(), runs.last_mut()) {
+                (Some(last), Some(run)) if last == base => *run += 1,
+                _ => {
+                    compressed.push(*base);
+                    runs.push(1);
+                }
+            }
+        }
+        (compressed, runs)
     }
-}
 
-impl<'a> Sequence<'a> for Cow<'a, [u8]> {
-    fn sequence(&'a self) -> &'a [u8] {
-        &self
-    }
-}
+    /// Shuffle the sequence while keeping its dinucleotide (2-mer) counts
+    /// the same, e.g. to make null models for motif finding, with the
+    /// Altschul-Erickson algorithm: the sequence is an Eulerian path through
+    /// the graph of its dinucleotides and a random one of those paths is
+    /// picked. The first and last bases stay where they are. The same
+    /// `seed` always gives the same shuffle.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let shuffled = b"ACGTTGCAACGT".dinucleotide_shuffle(1);
+    /// assert_eq!(shuffled.len(), 12);
+    /// assert_eq!(shuffled[0], b'A');
+    /// ```
+    fn dinucleotide_shuffle(&'a self, seed: u64) -> Vec<u8> {
+        let seq = self.sequence();
+        if seq.len() < 3 {
+            return seq.to_vec();
+        }
+        let mut rng = SplitMix64::new(seed);
+        let mut pick = |n: usize| (rng.next_u64() % n as u64) as usize;
 
-/// [⚠️Unstable] A trait to wrap over sequence data that has associated
-/// quality information.
-///
-/// Will be stabilized once we figure out a good way to handle sequences that
-/// have _optional_ quality information (like SequenceRecord) because the
-/// return trait requires a slice from an immutable reference and
-/// SequenceRecords can't return that without modifying themselves.
-pub trait QualitySequence<'a>: Sequence<'a> {
-    fn quality(&'a self) -> &'a [u8];
+        // the bases that follow each base
+        let mut edges: Vec<Vec<u8>> = vec![Vec::new(); 256];
+        for pair in seq.windows(2) {
+            edges[pair[0] as usize].push(pair[1]);
+        }
+        // pick the last edge out of every base so that following them
+        // always ends up at the last base (a random spanning tree rooted
+        // there, with Wilson's algorithm); those edges have to go last for
+        // the path to use every edge
+        let last = seq[seq.len() - 1];
+        let mut in_tree = [false; 256];
+        in_tree[last as usize] = true;
+        let mut last_edge = [0usize; 256];
+        for &start in seq {
+            let mut base = start;
+            while !in_tree[base as usize] {
+                let i = pick(edges[base as usize].len());
+                last_edge[base as usize] = i;
+                base = edges[base as usize][i];
+            }
+            let mut base = start;
+            while !in_tree[base as usize] {
+                in_tree[base as usize] = true;
+                base = edges[base as usize][last_edge[base as usize]];
+            }
+        }
+        // shuffle the rest of the edges out of each base
+        for (base, targets) in edges.iter_mut().enumerate() {
+            if targets.is_empty() {
+                continue;
+            }
+            let n = targets.len();
+            if base != last as usize {
+                targets.swap(last_edge[base], n - 1);
+            }
+            let n_shuffled = if base == last as usize { n } else { n - 1 };
+            for i in (1..n_shuffled).rev() {
+                let j = pick(i + 1);
+                targets.swap(i, j);
+            }
+            // so they can be popped off in order
+            targets.reverse();
+        }
 
-    /// Given a SeqRecord and a quality cutoff, mask out low-quality bases with
-    /// `N` characters.
-    fn quality_mask(&'a self, score: u8) -> Cow<'a, [u8]> {
-        let qual = self.quality();
-        // could maybe speed this up by doing a copy of base and then
-        // iterating though qual and masking?
-        let seq: Vec<u8> = self
-            .sequence()
-            .iter()
-            .zip(qual.iter())
-            .map(|(base, qual)| if *qual < score { b'N' } else { *base })
-            .collect();
-        seq.into()
+        let mut shuffled = Vec::with_capacity(seq.len());
+        let mut base = seq[0];
+        shuffled.push(base);
+        while let Some(next) = edges[base as usize].pop() {
+            shuffled.push(next);
+            base = next;
+        }
+        shuffled
     }
-}
 
-impl<'a> Sequence<'a> for (&'a [u8], &'a [u8]) {
-    fn sequence(&'a self) -> &'a [u8] {
-        &self.0
+    /// [Nucleic Acids] A stable 128-bit fingerprint of the sequence for
+    /// finding duplicate sequences across files. Case, whitespace (and line
+    /// endings) and U vs. T are ignored so the same sequence always has the
+    /// same fingerprint, however it's wrapped. The fingerprint is the 128-bit
+    /// FNV-1a hash of the uppercased sequence and won't change between
+    /// needletail versions.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGT".fingerprint(), 138924480566979687222879184937355942702);
+    /// assert_eq!(b"ac\ngu".fingerprint(), b"ACGT".fingerprint());
+    /// ```
+    fn fingerprint(&'a self) -> u128 {
+        fnv1a_128(
+            self.sequence()
+                .iter()
+                .filter(|c| !is_whitespace(**c))
+                .map(|c| match c.to_ascii_uppercase() {
+                    b'U' => b'T',
+                    c => c,
+                }),
+        )
     }
-}
 
-impl<'a> QualitySequence<'a> for (&'a [u8], &'a [u8]) {
-    fn quality(&'a self) -> &'a [u8] {
-        &self.1
+    /// [Nucleic Acids] Returns the `(start, end)` range of the sequence
+    /// left after removing any runs of N's (or n's) from its ends; N's in
+    /// the middle of the sequence are left alone. The range can also be used
+    /// to slice the quality scores to match. An all-N sequence gives `(0, 0)`.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let (seq, qual) = (b"NNACNGTn", b"##IIIII#");
+    /// let (start, end) = seq.trim_n();
+    /// assert_eq!(&seq[start..end], b"ACNGT");
+    /// assert_eq!(&qual[start..end], b"IIIII");
+    /// ```
+    fn trim_n(&'a self) -> (usize, usize) {
+        let seq = self.sequence();
+        let is_n = |c: &u8| c == &b'N' || c == &b'n';
+        match seq.iter().position(|c| !is_n(c)) {
+            Some(start) => {
+                let end = seq.iter().rposition(|c| !is_n(c)).unwrap() + 1;
+                (start, end)
+            }
+            None => (0, 0),
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_complement() {
-        assert_eq!(complement(b'a'), b't');
+    /// [Nucleic Acids] Count the bases that aren't A, C, G or T (ignoring
+    /// case), e.g. `N`s and other IUPAC ambiguity codes. Anything else in
+    /// the sequence (like gaps or `U`) is counted too.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGTNRacgtn".count_ambiguous(), 3);
+    /// ```
+    fn count_ambiguous(&'a self) -> usize {
+        self.sequence()
+            .iter()
+            .filter(|c| !matches!(c.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T'))
+            .count()
+    }
+
+    /// [Nucleic Acids] The fraction of the sequence that's ambiguous (see
+    /// `count_ambiguous`), e.g. for filtering out reads with too many `N`s.
+    /// Returns NaN for an empty sequence.
+    fn ambiguous_fraction(&'a self) -> f64 {
+        let seq = self.sequence();
+        if seq.is_empty() {
+            return f64::NAN;
+        }
+        self.count_ambiguous() as f64 / seq.len() as f64
+    }
+
+    /// [Nucleic Acids] Returns the GC skew, `(G - C) / (G + C)`, of the
+    /// sequence. This is NaN if there are no G or C bases.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"GGGC".gc_skew(), 0.5);
+    /// assert!(b"ATAT".gc_skew().is_nan());
+    /// ```
+    fn gc_skew(&'a self) -> f64 {
+        gc_skew(self.sequence())
+    }
+
+    /// [Nucleic Acids] Returns the GC skew of each `window`-sized window of
+    /// the sequence, starting every `step` bases (e.g. for finding origins
+    /// of replication). Windows without any G or C bases have a skew of
+    /// NaN and trailing bases that don't fill a whole window are ignored.
+    ///
+    /// Panics if `window` or `step` is 0.
+    fn gc_skew_windows(&'a self, window: usize, step: usize) -> Vec<f64> {
+        assert!(step > 0, "step must be greater than 0");
+        self.sequence()
+            .windows(window)
+            .step_by(step)
+            .map(gc_skew)
+            .collect()
+    }
+
+    /// [Nucleic Acids] Returns the GC content of each `window`-sized window
+    /// of the sequence, starting every `step` bases, as `(start of the
+    /// window, GC content)` pairs (e.g. for plotting GC along a chromosome).
+    /// Only A, C, G and T (or U) bases are counted, so windows without any
+    /// of them have a GC content of NaN. Trailing bases that don't fill a
+    /// whole window are ignored.
+    ///
+    /// Panics if `window` or `step` is 0.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"GCGCATAT".gc_windows(4, 4), vec![(0, 1.), (4, 0.)]);
+    /// ```
+    fn gc_windows(&'a self, window: usize, step: usize) -> Vec<(usize, f64)> {
+        assert!(step > 0, "step must be greater than 0");
+        self.sequence()
+            .windows(window)
+            .step_by(step)
+            .enumerate()
+            .map(|(i, w)| (i * step, gc_fraction(w)))
+            .collect()
+    }
+
+    /// Returns the position of the first character in the sequence that's
+    /// not in `alphabet` (ignoring case) or `None` if they all are (e.g.
+    /// with `DNA_ALPHABET` or `PROTEIN_ALPHABET`).
+    ///
+    /// ```
+    /// use needletail::sequence::{Sequence, PROTEIN_ALPHABET};
+    ///
+    /// assert_eq!(b"MVLS*".find_invalid(PROTEIN_ALPHABET), None);
+    /// assert_eq!(b"MV1S".find_invalid(PROTEIN_ALPHABET), Some(2));
+    /// ```
+    fn find_invalid(&'a self, alphabet: &[u8]) -> Option<usize> {
+        let mut allowed = [false; 256];
+        for c in alphabet {
+            allowed[c.to_ascii_uppercase() as usize] = true;
+        }
+        self.sequence()
+            .iter()
+            .position(|c| !allowed[c.to_ascii_uppercase() as usize])
+    }
+
+    /// Count the positions that differ (ignoring case) between the sequence
+    /// and `other`, i.e. the Hamming distance between them; only the
+    /// prefix they have in common is compared if their lengths differ.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGTAC".count_differences_to(b"acTTA"), 1);
+    /// ```
+    fn count_differences_to(&'a self, other: &[u8]) -> usize {
+        self.sequence()
+            .iter()
+            .zip(other)
+            .filter(|(a, b)| !a.eq_ignore_ascii_case(b))
+            .count()
+    }
+
+    /// Find the start of every exact (case-sensitive) occurrence of `needle`
+    /// in the sequence, e.g. to scan for restriction sites. If `overlapping`
+    /// is false the search for the next match starts after the end of the
+    /// previous one.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"AAAA".find_all(b"AA", true), vec![0, 1, 2]);
+    /// assert_eq!(b"AAAA".find_all(b"AA", false), vec![0, 2]);
+    /// ```
+    fn find_all(&'a self, needle: &[u8], overlapping: bool) -> Vec<usize> {
+        let seq = self.sequence();
+        let mut found = Vec::new();
+        if needle.is_empty() {
+            return found;
+        }
+        let mut pos = 0;
+        while pos + needle.len() <= seq.len() {
+            match memchr(needle[0], &seq[pos..=seq.len() - needle.len()]) {
+                Some(i) => {
+                    let start = pos + i;
+                    if &seq[start..start + needle.len()] == needle {
+                        found.push(start);
+                        pos = if overlapping {
+                            start + 1
+                        } else {
+                            start + needle.len()
+                        };
+                    } else {
+                        pos = start + 1;
+                    }
+                }
+                None => break,
+            }
+        }
+        found
+    }
+
+    /// [Nucleic Acids] Simulate a restriction digest: cut the sequence
+    /// `cut_offset` bases into every occurrence of the recognition `site`
+    /// (which can have IUPAC codes in it, see `iupac_match`) and return the
+    /// fragments. A sequence without any sites comes back whole.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// // EcoRI cuts G^AATTC
+    /// let fragments = b"AAGAATTCTT".digest(b"GAATTC", 1);
+    /// assert_eq!(fragments, vec![&b"AAG"[..], b"AATTCTT"]);
+    /// ```
+    fn digest(&'a self, site: &[u8], cut_offset: usize) -> Vec<&'a [u8]> {
+        let seq = self.sequence();
+        let mut fragments = Vec::new();
+        let mut last_cut = 0;
+        if !site.is_empty() && site.len() <= seq.len() {
+            for start in 0..=seq.len() - site.len() {
+                let window = &seq[start..start + site.len()];
+                if !site.iter().zip(window).all(|(c, b)| iupac_match(*c, *b)) {
+                    continue;
+                }
+                let cut = (start + cut_offset).min(seq.len());
+                if cut > last_cut {
+                    fragments.push(&seq[last_cut..cut]);
+                    last_cut = cut;
+                }
+            }
+        }
+        if last_cut < seq.len() || fragments.is_empty() {
+            fragments.push(&seq[last_cut..]);
+        }
+        fragments
+    }
+
+    /// Find where an adapter (or primer) starts towards the 3' end of the
+    /// sequence, allowing up to `max_mismatch` mismatched bases (ignoring
+    /// case). The adapter can run off the end of the sequence, in which case
+    /// only the overlapping part has to match and at least `min_overlap`
+    /// bases have to overlap. Returns the leftmost matching position (so
+    /// everything from there on can be trimmed off).
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// // the first 4 bases of the adapter are at the end of the read
+    /// assert_eq!(b"ACGTACGTAGAT".find_adapter(b"AGATCGGAAG", 3, 0), Some(8));
+    /// ```
+    fn find_adapter(
+        &'a self,
+        adapter: &[u8],
+        min_overlap: usize,
+        max_mismatch: usize,
+    ) -> Option<usize> {
+        let seq = self.sequence();
+        // an overlap shorter than this could never match
+        let min_overlap = min_overlap.max(1).min(adapter.len());
+        if adapter.is_empty() || seq.len() < min_overlap {
+            return None;
+        }
+        (0..=seq.len() - min_overlap).find(|&start| {
+            let mut mismatches = 0;
+            // `zip` cuts the adapter off at the end of the sequence
+            for (s, a) in seq[start..].iter().zip(adapter) {
+                if !s.eq_ignore_ascii_case(a) {
+                    mismatches += 1;
+                    if mismatches > max_mismatch {
+                        return false;
+                    }
+                }
+            }
+            true
+        })
+    }
+
+    /// [Nucleic Acids] Find the first place a primer matches the sequence
+    /// in full, allowing `max_mismatch` mismatches, and return the position
+    /// just past it so everything up to and including the primer (e.g. a 5'
+    /// adapter too) can be clipped off by slicing from there. IUPAC codes
+    /// in the primer match any of the bases they stand for (see
+    /// `iupac_match`). Returns `None` if the primer isn't found or is empty.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let read = b"AAGTGCCAGCAGCCGCGGTAATAC";
+    /// let start = read.trim_to_primer(b"GTGYCAGCMGCCGCGGTAA", 0).unwrap();
+    /// assert_eq!(&read[start..], b"TAC");
+    /// ```
+    fn trim_to_primer(&'a self, primer: &[u8], max_mismatch: usize) -> Option<usize> {
+        let seq = self.sequence();
+        if primer.is_empty() || seq.len() < primer.len() {
+            return None;
+        }
+        (0..=seq.len() - primer.len())
+            .find(|&start| {
+                let mut mismatches = 0;
+                for (s, p) in seq[start..].iter().zip(primer) {
+                    if !iupac_match(*p, *s) {
+                        mismatches += 1;
+                        if mismatches > max_mismatch {
+                            return false;
+                        }
+                    }
+                }
+                true
+            })
+            .map(|start| start + primer.len())
+    }
+
+    /// [Nucleic Acids] Returns an iterator over the sequence that skips
+    /// non-ACGT bases and returns a tuple containing (position, the
+    /// canonicalized kmer, if the sequence is the complement of the original).
+    fn canonical_kmers(&'a self, k: u8, reverse_complement: &'a [u8]) -> CanonicalKmers<'a> {
+        CanonicalKmers::new(self.sequence().as_ref(), reverse_complement, k)
+    }
+
+    /// Returns an iterator that returns a sliding window of k-sized
+    /// sequences (k-mers). Does not skip whitespace or correct bases in the
+    /// original sequence so `.normalize` or `.strip_returns` may be
+    /// appropriate to use first.
+    fn kmers(&'a self, k: u8) -> Kmers<'a> {
+        Kmers::new(self.sequence().as_ref(), k)
+    }
+
+    /// Like `kmers`, but treats the sequence as circular so the k-mers
+    /// that wrap around from the end to the start are returned too (see
+    /// `kmer::CircularKmers`).
+    fn circular_kmers(&'a self, k: u8) -> CircularKmers<'a> {
+        CircularKmers::new(self.sequence(), k)
+    }
+
+    /// Returns the k-mer starting at `pos` (the same one `kmers` would
+    /// return there) or `None` if it would run past the end of the sequence.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGTA".kmer_at(1, 3), Some(&b"CGT"[..]));
+    /// assert_eq!(b"ACGTA".kmer_at(3, 3), None);
+    /// ```
+    fn kmer_at(&'a self, pos: usize, k: u8) -> Option<&'a [u8]> {
+        self.sequence().get(pos..pos.checked_add(usize::from(k))?)
+    }
+
+    /// [Nucleic Acids] Returns an iterator over the codons (3-base slices)
+    /// of the sequence in the given reading frame (0, 1 or 2, i.e. the
+    /// offset of the first codon). An incomplete codon at the end of the
+    /// sequence is dropped.
+    ///
+    /// # Panics
+    ///
+    /// If `frame` isn't 0, 1 or 2.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let codons: Vec<&[u8]> = b"ATGGCC".codons(0).collect();
+    /// assert_eq!(codons, vec![b"ATG", b"GCC"]);
+    /// ```
+    fn codons(&'a self, frame: u8) -> ChunksExact<'a, u8> {
+        assert!(frame < 3, "frame must be 0, 1 or 2");
+        let seq = self.sequence();
+        seq[seq.len().min(frame as usize)..].chunks_exact(3)
+    }
+
+    /// [Nucleic Acids] Count how often each codon occurs in the given
+    /// reading frame (see `codons`). The table is indexed like
+    /// `nucleotide_kmer_table` for k=3 (AAA, AAC, AAG, ... TTT) and codons
+    /// containing anything other than A, C, G or T (ignoring case) are
+    /// skipped.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let usage = b"ATGATGTAA".codon_usage(0);
+    /// // ATG is 0b00_11_10
+    /// assert_eq!(usage[0b001110], 2);
+    /// ```
+    fn codon_usage(&'a self, frame: u8) -> [u32; 64] {
+        let mut table = [0; 64];
+        'codons: for codon in self.codons(frame) {
+            let mut idx = 0;
+            for base in codon {
+                idx = (idx << 2)
+                    + match base {
+                        b'A' | b'a' => 0,
+                        b'C' | b'c' => 1,
+                        b'G' | b'g' => 2,
+                        b'T' | b't' => 3,
+                        _ => continue 'codons,
+                    };
+            }
+            table[idx] += 1;
+        }
+        table
+    }
+
+    /// [Nucleic Acids] The fraction of bases at the third position of every
+    /// codon in the given reading frame (see `codons`) that are G or C
+    /// (ignoring case), i.e. the GC3 content. Returns NaN if there aren't
+    /// any complete codons.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ATGGCCAAGTTT".gc3(0), 0.75);
+    /// ```
+    fn gc3(&'a self, frame: u8) -> f64 {
+        let (mut gc, mut total) = (0usize, 0usize);
+        for codon in self.codons(frame) {
+            if let b'G' | b'g' | b'C' | b'c' = codon[2] {
+                gc += 1;
+            }
+            total += 1;
+        }
+        if total == 0 {
+            return f64::NAN;
+        }
+        gc as f64 / total as f64
+    }
+
+    /// [Nucleic Acids] Mask low-complexity regions (like DUST does): every
+    /// base in a `window` long stretch with a `base_entropy` below
+    /// `threshold` bits is masked. Sequences shorter than the window are
+    /// left alone.
+    ///
+    /// ```
+    /// use needletail::sequence::MaskMode;
+    /// use needletail::Sequence;
+    ///
+    /// let masked = b"ACGTAAAAAAAA".mask_low_complexity(6, 0.5, MaskMode::Hard);
+    /// assert_eq!(masked, b"ACGTNNNNNNNN");
+    /// ```
+    fn mask_low_complexity(&'a self, window: usize, threshold: f64, mode: MaskMode) -> Vec<u8> {
+        let seq = self.sequence();
+        let mut masked = seq.to_vec();
+        if window == 0 || seq.len() < window {
+            return masked;
+        }
+        let mut counts = [0; 5];
+        for base in &seq[..window - 1] {
+            counts[entropy_class(*base)] += 1;
+        }
+        // everything before this has already been masked
+        let mut masked_to = 0;
+        for start in 0..=seq.len() - window {
+            counts[entropy_class(seq[start + window - 1])] += 1;
+            if entropy_from_counts(&counts, window) < threshold {
+                for base in &mut masked[masked_to.max(start)..start + window] {
+                    *base = match mode {
+                        MaskMode::Soft => base.to_ascii_lowercase(),
+                        MaskMode::Hard => b'N',
+                    };
+                }
+                masked_to = start + window;
+            }
+            counts[entropy_class(seq[start])] -= 1;
+        }
+        masked
+    }
+
+    /// [Nucleic Acids] The fraction of the unmasked (uppercase) A, C, G and
+    /// T (or U) bases that are G or C; soft-masked (lowercase) bases, e.g.
+    /// repeats, are left out completely. Returns NaN if there aren't any
+    /// unmasked bases.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"GCATgggggg".gc_content_unmasked(), 0.5);
+    /// ```
+    fn gc_content_unmasked(&'a self) -> f64 {
+        let (mut gc, mut total) = (0usize, 0usize);
+        for base in self.sequence() {
+            match base {
+                b'G' | b'C' => {
+                    gc += 1;
+                    total += 1;
+                }
+                b'A' | b'T' | b'U' => total += 1,
+                _ => {}
+            }
+        }
+        if total == 0 {
+            return f64::NAN;
+        }
+        gc as f64 / total as f64
+    }
+
+    /// [Nucleic Acids] Count how many bases are soft-masked (lowercase),
+    /// hard-masked (`N`) or not masked at all (uppercase), e.g. to summarize
+    /// repeat masking. Anything that's not a letter (like gaps) isn't
+    /// counted.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// let stats = b"ACgtNN".masking_stats();
+    /// assert_eq!((stats.soft, stats.hard, stats.unmasked), (2, 2, 2));
+    /// ```
+    fn masking_stats(&'a self) -> MaskStats {
+        let mut stats = MaskStats::default();
+        for base in self.sequence() {
+            match base {
+                b'N' | b'n' => stats.hard += 1,
+                b'a'..=b'z' => stats.soft += 1,
+                b'A'..=b'Z' => stats.unmasked += 1,
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    /// [Nucleic Acids] Translate the sequence into amino acids in the given
+    /// reading frame (see `codons`); any partial codon at the end is left
+    /// off.
+    ///
+    /// ```
+    /// use needletail::translate::CodonTable;
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ATGGCCTAAG".translate(0, &CodonTable::standard()), b"MA*");
+    /// ```
+    fn translate(&'a self, frame: u8, table: &CodonTable) -> Vec<u8> {
+        self.codons(frame)
+            .map(|codon| table.translate_codon(codon))
+            .collect()
+    }
+
+    /// [Nucleic Acids] Translate the sequence in all six reading frames:
+    /// frames 0, 1 and 2 of the sequence and then frames 0, 1 and 2 of its
+    /// reverse complement.
+    fn translate_six_frames(&'a self, table: &CodonTable) -> [Vec<u8>; 6] {
+        let rc = self.reverse_complement();
+        [
+            self.translate(0, table),
+            self.translate(1, table),
+            self.translate(2, table),
+            rc.translate(0, table),
+            rc.translate(1, table),
+            rc.translate(2, table),
+        ]
+    }
+
+    /// [Nucleic Acids] Find the longest open reading frame (a start codon
+    /// up to the next in-frame stop; see `Orf`) in any frame of the
+    /// sequence, and also of its reverse complement if `both_strands` is
+    /// set. Reading frames that never reach a stop codon aren't counted.
+    /// Ties go to the forward strand and then the lowest frame.
+    ///
+    /// ```
+    /// use needletail::translate::CodonTable;
+    /// use needletail::Sequence;
+    ///
+    /// let orf = b"CCATGAAATAGG".longest_orf(&CodonTable::standard(), true).unwrap();
+    /// assert_eq!((orf.start, orf.end, orf.frame, orf.reverse), (2, 11, 2, false));
+    /// assert_eq!(b"ATGAAAAAA".longest_orf(&CodonTable::standard(), true), None);
+    /// ```
+    fn longest_orf(&'a self, table: &CodonTable, both_strands: bool) -> Option<Orf> {
+        let seq = self.sequence();
+        let forward = longest_forward_orf(seq, table).map(|(start, end, frame)| Orf {
+            start,
+            end,
+            frame,
+            reverse: false,
+        });
+        if !both_strands {
+            return forward;
+        }
+        let reverse =
+            longest_forward_orf(&self.reverse_complement(), table).map(|(start, end, frame)| Orf {
+                start: seq.len() - end,
+                end: seq.len() - start,
+                frame,
+                reverse: true,
+            });
+        match (forward, reverse) {
+            (Some(f), Some(r)) if r.len() > f.len() => Some(r),
+            (None, r) => r,
+            (f, _) => f,
+        }
+    }
+
+    /// [Nucleic Acids] Count every k-mer of the sequence into a dense table
+    /// with 4^k entries, indexed by the 2-bit encoding of the k-mer (A=0,
+    /// C=1, G=2, T=3 with the first base in the highest bits) so e.g. for
+    /// k=2 the entries are in the order AA, AC, AG, AT, CA, ... TT. K-mers
+    /// containing non-ACGT bases are skipped and case is ignored.
+    ///
+    /// This is meant for small k (e.g. di- or tri-nucleotide frequencies
+    /// for composition-based features); the table grows quickly with k.
+    ///
+    /// ```
+    /// use needletail::Sequence;
+    ///
+    /// assert_eq!(b"ACGTA".nucleotide_kmer_table(1), vec![2, 1, 1, 1]);
+    /// ```
+    fn nucleotide_kmer_table(&'a self, k: u8) -> Vec<u32> {
+        assert!(k > 0 && k < 32, "k must be between 1 and 31");
+        let mut table = vec![0; 1 << (2 * k)];
+        for (_, (kmer, _), _) in self.bit_kmers(k, false) {
+            table[kmer as usize] += 1;
+        }
+        table
+    }
+
+    /// Return an iterator that returns valid kmers in 4-bit form
+    fn bit_kmers(&'a self, k: u8, canonical: bool) -> BitNuclKmer<'a> {
+        BitNuclKmer::new(self.sequence(), k, canonical)
+    }
+}
+
+impl<'a> Sequence<'a> for &'a [u8] {
+    fn sequence(&'a self) -> &'a [u8] {
+        &self
+    }
+}
+
+impl<'a> Sequence<'a> for [u8] {
+    fn sequence(&'a self) -> &'a [u8] {
+        &self
+    }
+}
+
+impl<'a> Sequence<'a> for Cow<'a, [u8]> {
+    fn sequence(&'a self) -> &'a [u8] {
+        &self
+    }
+}
+
+/// [⚠️Unstable] A trait to wrap over sequence data that has associated
+/// quality information.
+///
+/// Will be stabilized once we figure out a good way to handle sequences that
+/// have _optional_ quality information (like SequenceRecord) because the
+/// return trait requires a slice from an immutable reference and
+/// SequenceRecords can't return that without modifying themselves.
+pub trait QualitySequence<'a>: Sequence<'a> {
+    fn quality(&'a self) -> &'a [u8];
+
+    /// Given a SeqRecord and a quality cutoff, mask out low-quality bases with
+    /// `N` characters.
+    fn quality_mask(&'a self, score: u8) -> Cow<'a, [u8]> {
+        let qual = self.quality();
+        // could maybe speed this up by doing a copy of base and then
+        // iterating though qual and masking?
+        let seq: Vec<u8> = self
+            .sequence()
+            .iter()
+            .zip(qual.iter())
+            .map(|(base, qual)| if *qual < score { b'N' } else { *base })
+            .collect();
+        seq.into()
+    }
+
+    /// Bin every quality score into one of a smaller set of levels, e.g.
+    /// with `QualityBinScheme::illumina_8_level()`.
+    fn bin_quality(&'a self, scheme: &QualityBinScheme) -> Vec<u8> {
+        self.quality().iter().map(|q| scheme.bin(*q)).collect()
+    }
+
+    /// Work out the `(start, end)` range of the read to keep after doing all
+    /// of the trims in `options`: quality trimming both ends (skipped if
+    /// there aren't any quality scores), then cutting off an adapter and
+    /// then a poly-A tail. Each trim only looks at what's left after the
+    /// ones before it. A read that's trimmed away completely gives an empty
+    /// range.
+    ///
+    /// ```
+    /// use needletail::trim::TrimOptions;
+    /// use needletail::sequence::QualitySequence;
+    ///
+    /// let read = (&b"ACGTACGTAAAAAA"[..], &b"IIIIIIIIIIII##"[..]);
+    /// let options = TrimOptions::new().quality_cutoff(Some(20)).poly_a(Some(3));
+    /// assert_eq!(read.trim(&options), (0, 8));
+    /// ```
+    fn trim(&'a self, options: &TrimOptions) -> (usize, usize) {
+        let (mut start, mut end) = (0, self.sequence().len());
+        for annotation in self.trim_annotations(options) {
+            start += annotation.five_prime;
+            end -= annotation.three_prime;
+        }
+        (start, end)
+    }
+
+    /// Like `trim`, but says what each trim would cut off the read (and
+    /// why) instead of just the range that's left, e.g. for recording trims
+    /// without applying them. Trims that wouldn't cut anything are left
+    /// out, so an untrimmed read has no annotations.
+    ///
+    /// ```
+    /// use needletail::trim::{TrimAnnotation, TrimOptions, TrimReason};
+    /// use needletail::sequence::QualitySequence;
+    ///
+    /// let read = (&b"ACGTACGTAAAAAA"[..], &b"IIIIIIIIIIII##"[..]);
+    /// let options = TrimOptions::new().quality_cutoff(Some(20)).poly_a(Some(3));
+    /// let annotations = read.trim_annotations(&options);
+    /// assert_eq!(annotations[0], TrimAnnotation::new(0, 2, TrimReason::Quality));
+    /// assert_eq!(annotations[1], TrimAnnotation::new(0, 4, TrimReason::PolyA));
+    /// ```
+    fn trim_annotations(&'a self, options: &TrimOptions) -> Vec<TrimAnnotation> {
+        let seq = self.sequence();
+        let qual = self.quality();
+        let mut annotations = Vec::new();
+        let (mut start, mut end) = (0, seq.len());
+        let mut annotate = |five_prime: usize, three_prime: usize, reason: TrimReason| {
+            if five_prime > 0 || three_prime > 0 {
+                annotations.push(TrimAnnotation::new(five_prime, three_prime, reason));
+            }
+        };
+        if let Some(cutoff) = options.quality_cutoff {
+            if qual.len() == seq.len() {
+                // (a read that's all low quality is trimmed from the 3' end)
+                let (s, e) = quality_trim(qual, cutoff);
+                annotate(s, seq.len() - e, TrimReason::Quality);
+                start = s;
+                end = e;
+            }
+        }
+        if let Some(adapter) = &options.adapter {
+            if let Some(pos) = seq[start..end].find_adapter(
+                adapter,
+                options.adapter_min_overlap,
+                options.adapter_max_mismatch,
+            ) {
+                annotate(0, end - start - pos, TrimReason::Adapter);
+                end = start + pos;
+            }
+        }
+        if let Some(min_len) = options.poly_a {
+            let run = seq[start..end]
+                .iter()
+                .rev()
+                .take_while(|b| **b == b'A' || **b == b'a')
+                .count();
+            if run >= min_len.max(1) {
+                annotate(0, run, TrimReason::PolyA);
+            }
+        }
+        annotations
+    }
+}
+
+impl<'a> Sequence<'a> for (&'a [u8], &'a [u8]) {
+    fn sequence(&'a self) -> &'a [u8] {
+        &self.0
+    }
+}
+
+impl<'a> QualitySequence<'a> for (&'a [u8], &'a [u8]) {
+    fn quality(&'a self) -> &'a [u8] {
+        &self.1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complement() {
+        assert_eq!(complement(b'a'), b't');
         assert_eq!(complement(b'c'), b'g');
         assert_eq!(complement(b'g'), b'c');
         assert_eq!(complement(b'n'), b'n');
     }
 
+    #[test]
+    fn test_can_pair() {
+        assert!(can_pair(b'A', b'U'));
+        assert!(can_pair(b'u', b'a'));
+        assert!(can_pair(b'G', b'C'));
+        assert!(can_pair(b'C', b'g'));
+        // wobble pairs
+        assert!(can_pair(b'G', b'U'));
+        assert!(can_pair(b'U', b'G'));
+        assert!(can_pair(b'G', b'T'));
+
+        assert!(!can_pair(b'A', b'C'));
+        assert!(!can_pair(b'A', b'A'));
+        assert!(!can_pair(b'N', b'N'));
+
+        assert!(b"GGGAUCCC".is_palindromic_rna());
+        assert!(b"GGGAUUCCC".is_palindromic_rna());
+        // G-U wobble at the ends
+        assert!(b"GCAUGU".is_palindromic_rna());
+        assert!(!b"GCAAGC".is_palindromic_rna());
+        assert!(b"".is_palindromic_rna());
+    }
+
+    #[test]
+    fn test_rev_comp_iter() {
+        let seq = &b"ACGTRYKMacgtNn-"[..];
+        let rc: Vec<u8> = seq.rev_comp_iter().collect();
+        assert_eq!(rc, seq.reverse_complement());
+        assert_eq!(rc, b"-nNacgtKMRYACGT");
+        assert_eq!(seq.rev_comp_iter().len(), seq.len());
+    }
+
     #[test]
     fn can_canonicalize() {
         assert!(canonical(b"A") == Cow::Borrowed(b"A"));
@@ -359,6 +1612,616 @@ mod tests {
         assert!(canonical(b"GC") == Cow::Borrowed(b"GC"));
     }
 
+    #[test]
+    fn test_canonical_sequence() {
+        let seq = &b"TTAGC"[..];
+        assert_eq!(Sequence::canonical(&seq), (b"GCTAA".to_vec(), true));
+        assert_eq!(
+            Sequence::canonical(&&b"GCTAA"[..]),
+            (b"GCTAA".to_vec(), false)
+        );
+
+        // palindromes are their own reverse complement
+        let seq = &b"ACGT"[..];
+        assert_eq!(Sequence::canonical(&seq), (b"ACGT".to_vec(), false));
+    }
+
+    #[test]
+    fn test_find_adapter() {
+        let adapter = b"AGATCGGAAG";
+
+        // the whole adapter is inside the read (with one mismatch)
+        let seq = &b"ACGTACGTAGATCGCAAGTTTT"[..];
+        assert_eq!(seq.find_adapter(adapter, 5, 1), Some(8));
+        assert_eq!(seq.find_adapter(adapter, 5, 0), None);
+
+        // the adapter runs off of the 3' end of the read
+        let seq = &b"ACGTACGTAGATC"[..];
+        assert_eq!(seq.find_adapter(adapter, 3, 0), Some(8));
+        assert_eq!(seq.find_adapter(adapter, 6, 0), None);
+        assert_eq!(seq.find_adapter(b"agatcggaag", 3, 0), Some(8));
+
+        // no adapter at all
+        let seq = &b"CCCCCCCCCCCC"[..];
+        assert_eq!(seq.find_adapter(adapter, 3, 1), None);
+        assert_eq!(seq.find_adapter(b"", 3, 1), None);
+    }
+
+    #[test]
+    fn test_trim_to_primer() {
+        // 515F (GTGYCAGCMGCCGCGGTAA) after a couple of adapter bases
+        let primer = b"GTGYCAGCMGCCGCGGTAA";
+        let read = &b"ACGTGTCAGCCGCCGCGGTAATACGTAG"[..];
+        let start = read.trim_to_primer(primer, 0).unwrap();
+        assert_eq!(start, 21);
+        assert_eq!(&read[start..], b"TACGTAG");
+        assert_eq!(
+            b"acgtgccagcagccgcggtaatac".trim_to_primer(primer, 0),
+            Some(21)
+        );
+
+        // with a mismatch (C for A in the last base)
+        let read = &b"GTGCCAGCAGCCGCGGTACTACG"[..];
+        assert_eq!(read.trim_to_primer(primer, 0), None);
+        assert_eq!(read.trim_to_primer(primer, 1), Some(19));
+
+        // an N in the read doesn't match anything more specific
+        assert_eq!(b"GTNCCAGCAGCCGCGGTAA".trim_to_primer(primer, 0), None);
+        assert_eq!(b"GTGTCAG".trim_to_primer(primer, 5), None);
+        assert_eq!(b"ACGT".trim_to_primer(b"", 0), None);
+    }
+
+    #[test]
+    fn test_codons() {
+        let seq = &b"ATGGCCTAAG"[..];
+        let codons: Vec<&[u8]> = seq.codons(0).collect();
+        assert_eq!(codons, vec![&b"ATG"[..], b"GCC", b"TAA"]);
+        let codons: Vec<&[u8]> = seq.codons(1).collect();
+        assert_eq!(codons, vec![&b"TGG"[..], b"CCT", b"AAG"]);
+        let codons: Vec<&[u8]> = seq.codons(2).collect();
+        assert_eq!(codons, vec![&b"GGC"[..], b"CTA"]);
+
+        assert_eq!(b"AT".codons(2).count(), 0);
+    }
+
+    #[test]
+    fn test_codon_usage() {
+        // M A A X * with a partial codon at the end
+        let seq = &b"ATGGCAgcaNNATAAG"[..];
+        let usage = seq.codon_usage(0);
+        // ATG
+        assert_eq!(usage[0b00_11_10], 1);
+        // GCA (in either case)
+        assert_eq!(usage[0b10_01_00], 2);
+        // TAA
+        assert_eq!(usage[0b11_00_00], 1);
+        // the codon with Ns is skipped
+        assert_eq!(usage.iter().sum::<u32>(), 4);
+
+        // TGG CAg caN NAT AAG
+        let usage = seq.codon_usage(1);
+        assert_eq!(usage[0b11_00_00], 0);
+        assert_eq!(usage[0b11_10_10], 1);
+        assert_eq!(usage.iter().sum::<u32>(), 3);
+    }
+
+    #[test]
+    fn test_case_conversion() {
+        let seq = &b"AcGu*-.xR\n\xff"[..];
+        assert_eq!(seq.to_uppercase(), b"ACGU*-.XR\n\xff");
+        assert_eq!(seq.to_lowercase(), b"acgu*-.xr\n\xff");
+        // the original is untouched
+        assert_eq!(seq, b"AcGu*-.xR\n\xff");
+    }
+
+    #[test]
+    fn test_trim() {
+        // a low quality base, the insert, a poly-A tail, an adapter and then
+        // some low quality junk
+        let seq = b"GACGTCGTAGCTAGCAAAAAAAACTGTCTCTTATATT".to_vec();
+        let mut qual = vec![b'I'; seq.len()];
+        qual[0] = b'#';
+        qual[35] = b'#';
+        qual[36] = b'#';
+        let read = (&seq[..], &qual[..]);
+
+        let options = TrimOptions::new()
+            .quality_cutoff(Some(20))
+            .adapter(Some(b"CTGTCTCTTATACACATCT".to_vec()), 3, 0)
+            .poly_a(Some(5));
+        let (start, end) = read.trim(&options);
+        assert_eq!((start, end), (1, 15));
+        assert_eq!(&seq[start..end], b"ACGTCGTAGCTAGC");
+
+        // each trim on its own
+        assert_eq!(
+            read.trim(&TrimOptions::new().quality_cutoff(Some(20))),
+            (1, 35)
+        );
+        let adapter_only = TrimOptions::new().adapter(Some(b"CTGTCTCTTATA".to_vec()), 3, 0);
+        assert_eq!(read.trim(&adapter_only), (0, 23));
+        // the poly-A isn't at the end until the adapter's gone
+        assert_eq!(read.trim(&TrimOptions::new().poly_a(Some(5))), (0, 37));
+        assert_eq!(read.trim(&TrimOptions::new()), (0, 37));
+
+        // everything trimmed away
+        let read = (&b"AAAA"[..], &b"IIII"[..]);
+        assert_eq!(read.trim(&TrimOptions::new().poly_a(Some(2))), (0, 0));
+        let read = (&b"ACGT"[..], &b"##!#"[..]);
+        assert_eq!(
+            read.trim(&TrimOptions::new().quality_cutoff(Some(20))),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_trim_annotations() {
+        let read = (&b"ACGTACGTACGT"[..], &b"#!IIIIIIII+#"[..]);
+        let options = TrimOptions::new().quality_cutoff(Some(20));
+        assert_eq!(
+            read.trim_annotations(&options),
+            vec![TrimAnnotation::new(2, 2, TrimReason::Quality)]
+        );
+        assert_eq!(read.trim(&options), (2, 10));
+
+        // the adapter's cut relative to what's left after quality trimming
+        let options = options.adapter(Some(b"GTACTTTT".to_vec()), 4, 0);
+        assert_eq!(
+            read.trim_annotations(&options),
+            vec![
+                TrimAnnotation::new(2, 2, TrimReason::Quality),
+                TrimAnnotation::new(0, 4, TrimReason::Adapter)
+            ]
+        );
+        assert_eq!(read.trim(&options), (2, 6));
+
+        // nothing to trim
+        let read = (&b"ACGT"[..], &b"IIII"[..]);
+        assert!(read.trim_annotations(&options).is_empty());
+        let read = (&b"ACGT"[..], &b"####"[..]);
+        assert_eq!(
+            read.trim_annotations(&options),
+            vec![TrimAnnotation::new(0, 4, TrimReason::Quality)]
+        );
+    }
+
+    #[test]
+    fn test_mask_low_complexity() {
+        assert_eq!(base_entropy(b"ACGT"), 2.);
+        assert_eq!(base_entropy(b"aaaa"), 0.);
+        assert_eq!(base_entropy(b"AT"), 1.);
+        assert!(base_entropy(b"").is_nan());
+
+        let seq = &b"ACGTTGCAAGCTAGCTAGGATCCAAAAAAAAAAAAAAAAACGATCGTAGCTAGCATCGA"[..];
+        let masked = seq.mask_low_complexity(10, 1., MaskMode::Soft);
+        assert_eq!(
+            &masked[..],
+            &b"ACGTTGCAAGCTAGCTAGGATccaaaaaaaaaaaaaaaaacgaTCGTAGCTAGCATCGA"[..]
+        );
+        let masked = seq.mask_low_complexity(10, 1., MaskMode::Hard);
+        assert_eq!(masked.iter().filter(|b| **b == b'N').count(), 22);
+
+        // a complex sequence is left intact
+        let seq = &b"ACGTTGCAAGCTAGCTAGGATCCAGTC"[..];
+        assert_eq!(seq.mask_low_complexity(10, 1.5, MaskMode::Soft), seq);
+        // as are short ones
+        assert_eq!(b"AAAA".mask_low_complexity(10, 1., MaskMode::Hard), b"AAAA");
+    }
+
+    #[test]
+    fn test_find_all() {
+        let seq = &b"AAAA"[..];
+        assert_eq!(seq.find_all(b"AA", true), vec![0, 1, 2]);
+        assert_eq!(seq.find_all(b"AA", false), vec![0, 2]);
+        assert_eq!(seq.find_all(b"AAAAA", true), Vec::<usize>::new());
+        assert_eq!(seq.find_all(b"", true), Vec::<usize>::new());
+
+        let seq = &b"GAATTCAGAATTCGAATTCgaattc"[..];
+        assert_eq!(seq.find_all(b"GAATTC", false), vec![0, 7, 13]);
+        assert_eq!(b"ATATATA".find_all(b"ATA", true), vec![0, 2, 4]);
+        assert_eq!(b"ATATATA".find_all(b"ATA", false), vec![0, 4]);
+    }
+
+    #[test]
+    fn test_digest() {
+        assert!(iupac_match(b'R', b'a'));
+        assert!(iupac_match(b'N', b'U'));
+        assert!(iupac_match(b'R', b'R'));
+        assert!(!iupac_match(b'R', b'C'));
+        assert!(!iupac_match(b'A', b'N'));
+        assert!(!iupac_match(b'N', b'-'));
+
+        // EcoRI
+        let seq = &b"AAAGAATTCTTTTGAATTCCC"[..];
+        assert_eq!(
+            seq.digest(b"GAATTC", 1),
+            vec![&b"AAAG"[..], b"AATTCTTTTG", b"AATTCCC"]
+        );
+        // no sites
+        assert_eq!(b"ACGTACGT".digest(b"GAATTC", 1), vec![&b"ACGTACGT"[..]]);
+        assert_eq!(b"".digest(b"GAATTC", 1), vec![&b""[..]]);
+        // a site right at the start doesn't give an empty fragment
+        assert_eq!(b"GAATTCA".digest(b"GAATTC", 0), vec![&b"GAATTCA"[..]]);
+
+        // HincII is GTY^RAC
+        let seq = &b"AGTCAACCGTTGACC"[..];
+        assert_eq!(
+            seq.digest(b"GTYRAC", 3),
+            vec![&b"AGTC"[..], b"AACCGTT", b"GACC"]
+        );
+    }
+
+    #[test]
+    fn test_gc_content_unmasked() {
+        // the lowercase runs are all GC and the uppercase third of the bases
+        // are GC; Ns aren't counted
+        let seq = &b"ggggccccATGNNATGcgcgcgcgccTAATGC"[..];
+        assert_eq!(seq.gc_content_unmasked(), 4. / 12.);
+        assert!(b"acgtacgt".gc_content_unmasked().is_nan());
+        assert!(b"".gc_content_unmasked().is_nan());
+    }
+
+    #[test]
+    fn test_wrap_sequence() {
+        assert_eq!(wrap_sequence(b"ACGTAC", 3), b"ACG\nTAC");
+        assert_eq!(wrap_sequence(b"ACGTACGT", 3), b"ACG\nTAC\nGT");
+        assert_eq!(wrap_sequence(b"ACGT", 10), b"ACGT");
+        assert_eq!(wrap_sequence(b"ACGT", 0), b"ACGT");
+        assert_eq!(wrap_sequence(b"", 3), b"");
+        // and it can be stripped back out
+        assert_eq!(
+            &wrap_sequence(b"ACGTACGT", 3).strip_returns()[..],
+            b"ACGTACGT"
+        );
+    }
+
+    #[test]
+    fn test_masking_stats() {
+        let stats = b"ACGTacgtaNNNnnAC-GT*t".masking_stats();
+        assert_eq!(
+            stats,
+            MaskStats {
+                soft: 6,
+                hard: 5,
+                unmasked: 8
+            }
+        );
+        assert_eq!(b"".masking_stats(), MaskStats::default());
+    }
+
+    #[test]
+    fn test_circular_kmers() {
+        let seq = &b"ATGCCGTA"[..];
+        let kmers: Vec<_> = seq.circular_kmers(4).collect();
+        assert_eq!(kmers.len(), seq.len());
+        // the linear k-mers come first and are borrowed
+        let linear: Vec<_> = seq.kmers(4).collect();
+        assert_eq!(&kmers[..5], &linear[..]);
+        assert!(kmers[..5].iter().all(|k| matches!(k, Cow::Borrowed(_))));
+        // followed by the ones spanning the origin
+        assert_eq!(kmers[5], &b"GTAA"[..]);
+        assert_eq!(kmers[6], &b"TAAT"[..]);
+        assert_eq!(kmers[7], &b"AATG"[..]);
+
+        // k the same as the sequence length gives every rotation
+        let kmers: Vec<_> = b"ACG".circular_kmers(3).collect();
+        assert_eq!(kmers, vec![&b"ACG"[..], b"CGA", b"GAC"]);
+        // and a longer k gives nothing
+        assert_eq!(b"ACG".circular_kmers(4).count(), 0);
+        assert_eq!(b"".circular_kmers(1).count(), 0);
+    }
+
+    #[test]
+    fn test_kmer_at() {
+        let seq = &b"ACGTACGT"[..];
+        for (i, kmer) in seq.kmers(4).enumerate() {
+            assert_eq!(seq.kmer_at(i, 4), Some(kmer));
+        }
+        assert_eq!(seq.kmer_at(4, 4), Some(&b"ACGT"[..]));
+        assert_eq!(seq.kmer_at(5, 4), None);
+        assert_eq!(seq.kmer_at(usize::MAX, 4), None);
+        assert_eq!(b"AC".kmer_at(0, 3), None);
+    }
+
+    #[test]
+    fn test_gc3() {
+        // ATG GCG AAc TAA
+        let seq = &b"ATGGCGAAcTAA"[..];
+        assert_eq!(seq.gc3(0), 0.75);
+        // TGG CGA AcT with a partial codon at the end
+        assert_eq!(seq.gc3(1), 1. / 3.);
+        assert!(b"AT".gc3(2).is_nan());
+    }
+
+    #[test]
+    fn test_translate_six_frames() {
+        let table = CodonTable::standard();
+        let seq = &b"ATGAAACGCATTAGCACCAC"[..];
+        let frames = seq.translate_six_frames(&table);
+        assert_eq!(frames[0], b"MKRIST");
+        assert_eq!(frames[1], b"*NALAP");
+        assert_eq!(frames[2], b"ETH*HH");
+
+        let rc = seq.reverse_complement();
+        for frame in 0..3 {
+            assert_eq!(frames[3 + frame as usize], rc.translate(frame, &table));
+        }
+        // GTG GTG CTA ATG CGT TTC AT
+        assert_eq!(frames[3], b"VVLMRF");
+    }
+
+    #[test]
+    fn test_homopolymer_compress() {
+        let (hpc, runs) = b"AAACCG".homopolymer_compress();
+        assert_eq!(hpc, b"ACG");
+        assert_eq!(runs, vec![3, 2, 1]);
+
+        let seq = &b"TTTTGAAaCCCCCN"[..];
+        let (hpc, runs) = seq.homopolymer_compress();
+        assert_eq!(hpc, b"TGAaCN");
+        let mut rebuilt = Vec::new();
+        for (base, run) in hpc.iter().zip(runs) {
+            rebuilt.extend(vec![*base; run as usize]);
+        }
+        assert_eq!(rebuilt, seq);
+
+        assert_eq!(b"".homopolymer_compress(), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_dinucleotide_shuffle() {
+        use std::collections::HashMap;
+
+        let dinucleotides = |seq: &[u8]| {
+            let mut counts = HashMap::new();
+            for pair in seq.windows(2) {
+                *counts.entry(pair.to_vec()).or_insert(0) += 1;
+            }
+            counts
+        };
+
+        let seq = &b"ACGTTGCAACGTAGCTAGGATCCATTTACGGACTAGCA"[..];
+        let shuffled = seq.dinucleotide_shuffle(42);
+        assert_eq!(shuffled.len(), seq.len());
+        assert_ne!(&shuffled[..], seq);
+        assert_eq!(dinucleotides(&shuffled), dinucleotides(seq));
+        assert_eq!(shuffled[0], seq[0]);
+        assert_eq!(shuffled.last(), seq.last());
+        // the same seed gives the same shuffle and different ones don't
+        assert_eq!(seq.dinucleotide_shuffle(42), shuffled);
+        assert_ne!(seq.dinucleotide_shuffle(43), shuffled);
+
+        for seed in 0..20 {
+            let shuffled = b"AAAACAAGTTTT".dinucleotide_shuffle(seed);
+            assert_eq!(dinucleotides(&shuffled), dinucleotides(b"AAAACAAGTTTT"));
+        }
+        assert_eq!(b"AC".dinucleotide_shuffle(1), b"AC");
+        assert_eq!(b"".dinucleotide_shuffle(1), b"");
+    }
+
+    #[test]
+    fn test_head_tail() {
+        let seq = &b"ACGTA"[..];
+        assert_eq!(seq.head(2), b"AC");
+        assert_eq!(seq.tail(2), b"TA");
+        assert_eq!(seq.head(5), seq);
+        assert_eq!(seq.tail(5), seq);
+        assert_eq!(seq.head(6), seq);
+        assert_eq!(seq.tail(100), seq);
+        assert_eq!(seq.head(0), b"");
+        assert_eq!(seq.tail(0), b"");
+        assert_eq!(b"".tail(1), b"");
+    }
+
+    #[test]
+    fn test_split_at_gaps() {
+        let pieces: Vec<_> = b"ACGTNNNNNTTGA".split_at_gaps(5).collect();
+        assert_eq!(pieces, vec![(0, &b"ACGT"[..]), (9, &b"TTGA"[..])]);
+
+        // several gaps (including at the ends) and one that's too short
+        let seq = &b"NNNNACNNNNGTNNAAnnnnCC"[..];
+        let pieces: Vec<_> = seq.split_at_gaps(3).collect();
+        assert_eq!(
+            pieces,
+            vec![(4, &b"AC"[..]), (10, &b"GTNNAA"[..]), (20, &b"CC"[..])]
+        );
+        let pieces: Vec<_> = seq.split_at_gaps(5).collect();
+        assert_eq!(pieces, vec![(0, seq)]);
+
+        assert_eq!(b"NNNN".split_at_gaps(2).count(), 0);
+        assert_eq!(b"".split_at_gaps(2).count(), 0);
+    }
+
+    #[test]
+    fn test_strip_whitespace() {
+        let seq = &b"AC-GT\r\nA C\n"[..];
+        assert_eq!(&seq.strip_whitespace()[..], b"AC-GTAC");
+        assert_eq!(&seq.strip_returns()[..], b"AC-GTA C");
+        assert_eq!(&seq.strip_whitespace_keeping(b"-")[..], b"AC-GTAC");
+        assert_eq!(&seq.strip_whitespace_keeping(b" ")[..], b"AC-GTA C");
+
+        // nothing to strip, so the original is returned
+        let seq = &b"AC-GT"[..];
+        assert!(seq.strip_whitespace() == Cow::Borrowed(seq));
+
+        // tabs and other ASCII whitespace in sequence lines
+        let seq = &b"ACGT\tACGT\x0cAC\x0bGT\n"[..];
+        assert_eq!(&seq.strip_whitespace()[..], b"ACGTACGTACGT");
+        assert_eq!(&seq.strip_whitespace_keeping(b"\t")[..], b"ACGT\tACGTACGT");
+        for c in WHITESPACE {
+            assert!(is_whitespace(*c));
+        }
+        assert!(!is_whitespace(b'-'));
+    }
+
+    #[test]
+    fn test_gc_skew() {
+        assert_eq!(gc_skew(b"GGGC"), 0.5);
+        assert_eq!(gc_skew(b"ggcccc"), -1. / 3.);
+        assert!(gc_skew(b"").is_nan());
+
+        // G-rich then C-rich (like either side of an origin of replication)
+        let seq = &b"GGGGAATTCCCCAATT"[..];
+        assert_eq!(seq.gc_skew(), 0.);
+        assert_eq!(seq.gc_skew_windows(8, 4), vec![1., -1., -1.]);
+
+        // a window with no G or C
+        let skews = seq.gc_skew_windows(4, 2);
+        assert_eq!(skews.len(), 7);
+        assert_eq!(skews[0], 1.);
+        assert!(skews[2].is_nan());
+        assert_eq!(skews[4], -1.);
+
+        // windows larger than the sequence
+        assert!(seq.gc_skew_windows(20, 1).is_empty());
+    }
+
+    #[test]
+    fn test_gc_windows() {
+        // a GC-rich region in the middle
+        let seq = &b"ATATGCGCGGCCATNT"[..];
+        let windows = seq.gc_windows(4, 2);
+        assert_eq!(
+            windows,
+            vec![
+                (0, 0.),
+                (2, 0.5),
+                (4, 1.),
+                (6, 1.),
+                (8, 1.),
+                (10, 0.5),
+                (12, 0.)
+            ]
+        );
+        assert_eq!(
+            seq.gc_windows(6, 5),
+            vec![(0, 2. / 6.), (5, 1.), (10, 2. / 5.)]
+        );
+        // N isn't counted
+        assert_eq!(b"NNgN".gc_windows(4, 1), vec![(0, 1.)]);
+        let windows = b"ACGANNNN".gc_windows(4, 4);
+        assert_eq!(windows[0], (0, 0.5));
+        assert!(windows[1].1.is_nan());
+        assert!(b"ACG".gc_windows(4, 1).is_empty());
+    }
+
+    #[test]
+    fn test_longest_orf() {
+        let table = CodonTable::standard();
+        // ORFs of 9, 15 and 12 bases in frames 0, 1 and 2 and one of 27
+        // bases on the reverse strand
+        let seq = b"ATGAAATAACATGAAAAAAAAATAGCATGCCCCCCTGACTTAGGCGGCGGCGGCGGCGGCGGCCATA";
+        assert_eq!(
+            seq.longest_orf(&table, false),
+            Some(Orf {
+                start: 10,
+                end: 25,
+                frame: 1,
+                reverse: false
+            })
+        );
+        let orf = seq.longest_orf(&table, true).unwrap();
+        assert_eq!(
+            (orf.start, orf.end, orf.frame, orf.reverse),
+            (39, 66, 1, true)
+        );
+        assert_eq!(
+            seq[orf.start..orf.end]
+                .reverse_complement()
+                .translate(0, &table),
+            b"MAAAAAAA*"
+        );
+
+        // no stop codon, no start codon and too short
+        assert_eq!(b"ATGATGATG".longest_orf(&table, true), None);
+        assert_eq!(b"CCCTAACCC".longest_orf(&table, false), None);
+        assert_eq!(b"AT".longest_orf(&table, true), None);
+    }
+
+    #[test]
+    fn test_nucleotide_kmer_table() {
+        let table = b"AACGTNacgg".nucleotide_kmer_table(2);
+        assert_eq!(table.len(), 16);
+        let mut expected = vec![0; 16];
+        expected[0b00_00] = 1; // AA
+        expected[0b00_01] = 2; // AC
+        expected[0b01_10] = 2; // CG
+        expected[0b10_11] = 1; // GT
+        expected[0b10_10] = 1; // GG
+        assert_eq!(table, expected);
+
+        // nothing long enough to count
+        assert_eq!(b"ANA".nucleotide_kmer_table(2), vec![0; 16]);
+        assert_eq!(b"ACG".nucleotide_kmer_table(3)[0b01_10_11], 0);
+        assert_eq!(b"ACG".nucleotide_kmer_table(3)[0b00_01_10], 1);
+    }
+
+    #[test]
+    fn test_transcription() {
+        use crate::sequence_record::SequenceRecord;
+
+        let rec = SequenceRecord::new(b"test".to_vec().into(), b"ACGTNtacg-".to_vec().into(), None);
+        let rna = rec.to_rna();
+        assert_eq!(&rna[..], b"ACGUNuacg-");
+        assert_eq!(&rna.to_dna()[..], b"ACGTNtacg-");
+
+        // nothing to change
+        let seq = &b"ACGN"[..];
+        assert!(seq.to_rna() == Cow::Borrowed(seq));
+        assert!(seq.to_dna() == Cow::Borrowed(seq));
+    }
+
+    #[test]
+    fn test_find_invalid() {
+        assert_eq!(b"ACGTNacgtn".find_invalid(DNA_ALPHABET), None);
+        assert_eq!(b"ACGU".find_invalid(DNA_ALPHABET), Some(3));
+        assert_eq!(
+            b"ACDEFGHIKLMNPQRSTVWYXBZJUO*-".find_invalid(PROTEIN_ALPHABET),
+            None
+        );
+        assert_eq!(b"MVL SP".find_invalid(PROTEIN_ALPHABET), Some(3));
+    }
+
+    #[test]
+    fn test_count_ambiguous() {
+        assert_eq!(b"ACGTacgt".count_ambiguous(), 0);
+        assert_eq!(b"NNnYACGT".count_ambiguous(), 4);
+        assert_eq!(b"AC-GU".count_ambiguous(), 2);
+        assert_eq!(b"".count_ambiguous(), 0);
+
+        assert_eq!(b"ACGN".ambiguous_fraction(), 0.25);
+        assert_eq!(b"nnnn".ambiguous_fraction(), 1.);
+        assert!(b"".ambiguous_fraction().is_nan());
+    }
+
+    #[test]
+    fn test_trim_n() {
+        assert_eq!(b"NNNACGT".trim_n(), (3, 7));
+        assert_eq!(b"ACGTnnN".trim_n(), (0, 4));
+        assert_eq!(b"nACNNGTN".trim_n(), (1, 7));
+        assert_eq!(b"ACGT".trim_n(), (0, 4));
+        assert_eq!(b"NNnN".trim_n(), (0, 0));
+        assert_eq!(b"".trim_n(), (0, 0));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        use crate::formats::parse_sequence_reader;
+        use std::io::Cursor;
+
+        let mut fingerprints = Vec::new();
+        let data = b">a\nACGU\nacgt\n>b other\r\nACGTAC\r\nGT\r\n>c\nACGTACGA\n";
+        parse_sequence_reader(
+            Cursor::new(&data[..]),
+            |_| {},
+            |rec| fingerprints.push(rec.fingerprint()),
+        )
+        .unwrap();
+        assert_eq!(fingerprints[0], fingerprints[1]);
+        assert_ne!(fingerprints[0], fingerprints[2]);
+        assert_eq!(fingerprints[0], b"AC GT\nACGT".fingerprint());
+    }
+
     #[test]
     fn can_minimize() {
         let minmer = minimizer(&b"ATTTCG"[..], 3);
@@ -4,12 +4,17 @@
 //! Primarily used as a common intermediate for processing both FASTA and
 //! FASTQ data.
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::io::Write;
+use std::ops::Deref;
 
 use memchr::memchr;
 
+use crate::quality::phred33;
 use crate::sequence::{QualitySequence, Sequence};
-use crate::util::ParseError;
+use crate::util::{header_desc, ParseError, ParseErrorType, SplitMix64};
 
 /// Mask tabs in header lines to `|`s
 pub fn mask_header_tabs(id: &[u8]) -> Option<Vec<u8>> {
@@ -32,16 +37,209 @@ pub fn mask_header_utf8(id: &[u8]) -> Option<Vec<u8>> {
 
 /// An intermediate structure for handling sequence data and harmonizing both
 /// FASTA and FASTQ records into a common format.
+///
+/// Parsing never normalizes sequences: line endings are stripped out of
+/// FASTA sequences, but otherwise the bases (and their case) are exactly as
+/// they were in the file. `Sequence::normalize` gives a normalized copy only
+/// when it's needed (and only allocates if anything changed).
+///
+/// SequenceRecords deref to their sequence so slice methods can be called
+/// on them directly:
+///
+/// ```
+/// use needletail::SequenceRecord;
+///
+/// let rec = SequenceRecord::new_owned("test", "ACGT", None);
+/// assert_eq!(rec.len(), 4);
+/// assert!(rec.starts_with(b"AC"));
+/// ```
+///
+/// Records are compared, ordered and hashed by their id, then their
+/// sequence and then their quality scores (with records without any coming
+/// first), so they can be used in sets and maps keyed on their contents.
+/// Whether the data is borrowed or owned and the `checksum` (which only
+/// depends on how the record was laid out in the file) don't matter.
+#[derive(Debug)]
 pub struct SequenceRecord<'a> {
     pub id: Cow<'a, [u8]>,
     pub seq: Cow<'a, [u8]>,
     pub qual: Option<Cow<'a, [u8]>>,
+    /// A CRC32 of the record as it appeared in the file (only computed when
+    /// parsing with the `checksum` parse option)
+    pub checksum: Option<u32>,
+}
+
+/// A SequenceRecord that owns all of its data (e.g. one that was generated
+/// instead of parsed out of a file).
+pub type OwnedSequenceRecord = SequenceRecord<'static>;
+
+impl SequenceRecord<'static> {
+    /// Creates a new SequenceRecord that owns its data. Unlike
+    /// `SequenceRecordBuilder` this does not check that the quality scores
+    /// are the same length as the sequence.
+    pub fn new_owned<I, S>(id: I, seq: S, qual: Option<Vec<u8>>) -> Self
+    where
+        I: Into<Vec<u8>>,
+        S: Into<Vec<u8>>,
+    {
+        SequenceRecord::new(id.into().into(), seq.into().into(), qual.map(Cow::from))
+    }
 }
 
 impl<'a> SequenceRecord<'a> {
     /// Creates a new SequenceRecord
     pub fn new(id: Cow<'a, [u8]>, seq: Cow<'a, [u8]>, qual: Option<Cow<'a, [u8]>>) -> Self {
-        SequenceRecord { id, seq, qual }
+        SequenceRecord {
+            id,
+            seq,
+            qual,
+            checksum: None,
+        }
+    }
+
+    /// Copies any borrowed data so the record no longer depends on the
+    /// buffer it was parsed out of (e.g. to keep it around after the
+    /// parsing callback returns).
+    pub fn into_owned(self) -> SequenceRecord<'static> {
+        SequenceRecord {
+            id: Cow::Owned(self.id.into_owned()),
+            seq: Cow::Owned(self.seq.into_owned()),
+            qual: self.qual.map(|q| Cow::Owned(q.into_owned())),
+            checksum: self.checksum,
+        }
+    }
+
+    /// The id as a string for display (e.g. in logs); any invalid UTF-8 is
+    /// replaced with `U+FFFD`.
+    pub fn id_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.id)
+    }
+
+    /// The description after the first word of the id, if there is one
+    /// (e.g. `length=150` in `read1 length=150`)
+    pub fn desc(&self) -> Option<&[u8]> {
+        header_desc(&self.id)
+    }
+
+    /// The sequence as a string for display; see `id_str`.
+    pub fn seq_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.seq)
+    }
+
+    /// Reverses the sequence and quality scores (without complementing
+    /// anything, unlike `reverse_complement`).
+    pub fn reverse(&self) -> OwnedSequenceRecord {
+        let seq: Vec<u8> = self.seq.iter().rev().cloned().collect();
+        let qual = self
+            .qual
+            .as_ref()
+            .map(|q| q.iter().rev().cloned().collect());
+        SequenceRecord::new_owned(self.id.to_vec(), seq, qual)
+    }
+
+    /// Flips the record onto the other strand: the sequence is reverse
+    /// complemented and the quality scores are reversed to match it.
+    ///
+    /// ```
+    /// use needletail::SequenceRecord;
+    ///
+    /// let rec = SequenceRecord::new_owned("read", "AACG", Some(b"#!II".to_vec()));
+    /// let rc = rec.reverse_complement_record();
+    /// assert_eq!(&rc.seq[..], b"CGTT");
+    /// assert_eq!(&rc.qual.unwrap()[..], b"II!#");
+    /// ```
+    pub fn reverse_complement_record(&self) -> OwnedSequenceRecord {
+        let qual = self
+            .qual
+            .as_ref()
+            .map(|q| q.iter().rev().cloned().collect());
+        SequenceRecord::new_owned(self.id.to_vec(), self.seq.reverse_complement(), qual)
+    }
+
+    /// Truncates or pads the record to exactly `len` bases. Sequences are
+    /// padded with `pad` and quality scores (if there are any) with the
+    /// Phred score `pad_qual` (from 0 to 93; higher scores are padded with
+    /// 93).
+    ///
+    /// ```
+    /// use needletail::SequenceRecord;
+    ///
+    /// let rec = SequenceRecord::new_owned("read", "ACG", Some(b"III".to_vec()));
+    /// let fixed = rec.fix_length(5, b'N', 2);
+    /// assert_eq!(&fixed.seq[..], b"ACGNN");
+    /// assert_eq!(&fixed.qual.unwrap()[..], b"III##");
+    /// ```
+    pub fn fix_length(&self, len: usize, pad: u8, pad_qual: u8) -> OwnedSequenceRecord {
+        let fix = |data: &[u8], pad: u8| {
+            let mut fixed = data[..len.min(data.len())].to_vec();
+            fixed.resize(len, pad);
+            fixed
+        };
+        let qual = self.qual.as_ref().map(|q| fix(q, phred33(pad_qual)));
+        SequenceRecord::new_owned(self.id.to_vec(), fix(&self.seq, pad), qual)
+    }
+
+    /// Simulate a read pair from this record as a sequenced fragment: R1 is
+    /// the first `read_len` bases and R2 is the reverse complement of the
+    /// last `read_len` bases (both cut down to the fragment's length if
+    /// it's shorter). Both reads get a quality score of `phred` (from 0 to
+    /// 93; higher scores are written as 93) for every base and the
+    /// record's id with `/1` or `/2` on the end.
+    ///
+    /// ```
+    /// use needletail::SequenceRecord;
+    ///
+    /// let fragment = SequenceRecord::new_owned("frag", "AACCGGTTAC", None);
+    /// let (r1, r2) = fragment.simulate_pair(4, 30);
+    /// assert_eq!(&r1.seq[..], b"AACC");
+    /// assert_eq!(&r2.seq[..], b"GTAA");
+    /// assert_eq!(&r2.id[..], b"frag/2");
+    /// ```
+    pub fn simulate_pair(
+        &self,
+        read_len: usize,
+        phred: u8,
+    ) -> (OwnedSequenceRecord, OwnedSequenceRecord) {
+        let read_len = read_len.min(self.seq.len());
+        let read = |suffix: &[u8], seq: Vec<u8>| {
+            let mut id = self.id.to_vec();
+            id.extend_from_slice(suffix);
+            let qual = vec![phred33(phred); seq.len()];
+            SequenceRecord::new_owned(id, seq, Some(qual))
+        };
+        let r1 = read(b"/1", self.seq[..read_len].to_vec());
+        let r2 = read(
+            b"/2",
+            self.seq[self.seq.len() - read_len..].reverse_complement(),
+        );
+        (r1, r2)
+    }
+
+    /// Randomly drops bases (and their quality scores) from the record,
+    /// keeping each one with a probability of `keep_fraction`; e.g. to
+    /// simulate shorter reads. The same `seed` always drops the same bases.
+    ///
+    /// ```
+    /// use needletail::SequenceRecord;
+    ///
+    /// let rec = SequenceRecord::new_owned("read", "ACGTACGT", None);
+    /// assert_eq!(rec.subsample_bases(1., 42).seq, rec.seq);
+    /// assert!(rec.subsample_bases(0., 42).seq.is_empty());
+    /// ```
+    pub fn subsample_bases(&self, keep_fraction: f64, seed: u64) -> OwnedSequenceRecord {
+        let mut rng = SplitMix64::new(seed);
+        let keep: Vec<bool> = (0..self.seq.len())
+            .map(|_| rng.next_f64() < keep_fraction)
+            .collect();
+        let subsample = |data: &[u8]| -> Vec<u8> {
+            data.iter()
+                .zip(&keep)
+                .filter(|(_, k)| **k)
+                .map(|(b, _)| *b)
+                .collect()
+        };
+        let qual = self.qual.as_ref().map(|q| subsample(q));
+        SequenceRecord::new_owned(self.id.to_vec(), subsample(&self.seq), qual)
     }
 
     /// Fixes up potential problems with sequence headers including tabs being
@@ -72,12 +270,26 @@ impl<'a> SequenceRecord<'a> {
     /// Write this SequenceRecord to writer as a FASTQ with the provided line
     /// ending (ending should be either `\r\n` or preferably `\n`).
     pub fn write_fastq(&self, writer: &mut dyn Write, ending: &[u8]) -> Result<(), ParseError> {
+        self.write_fastq_with(writer, ending, false)
+    }
+
+    /// Like `write_fastq`, but optionally repeats the id on the `+` line
+    /// (which some tools require)
+    pub(crate) fn write_fastq_with(
+        &self,
+        writer: &mut dyn Write,
+        ending: &[u8],
+        repeat_id_on_plus: bool,
+    ) -> Result<(), ParseError> {
         writer.write_all(b"@")?;
         writer.write_all(&self.id)?;
         writer.write_all(ending)?;
         writer.write_all(&self.seq)?;
         writer.write_all(ending)?;
         writer.write_all(b"+")?;
+        if repeat_id_on_plus {
+            writer.write_all(&self.id)?;
+        }
         writer.write_all(ending)?;
         // this is kind of a hack, but we want to allow writing out sequences
         // that don't have qualitys so this will mask to "good" if the quality
@@ -92,12 +304,256 @@ impl<'a> SequenceRecord<'a> {
     }
 }
 
+/// Merge a pair of reads from opposite ends of a fragment that overlap in
+/// the middle (e.g. amplicons) into one read covering the whole fragment,
+/// like FLASH or PEAR do. R2 is reverse complemented and every overlap of
+/// at least `min_overlap` bases between the end of R1 and the start of the
+/// reverse complemented R2 is tried; the one with the lowest fraction of
+/// mismatches (and then the longest) wins, as long as it has at most
+/// `max_mismatch` mismatches. Returns `None` if no overlap is good enough.
+///
+/// In the overlap, the base with the higher quality score is used (R1's on
+/// ties) with the higher of the two scores. The merged read has R1's id and
+/// only has quality scores if both reads did.
+///
+/// ```
+/// use needletail::sequence_record::merge_pair;
+/// use needletail::SequenceRecord;
+///
+/// let r1 = SequenceRecord::new_owned("r", "ACGTTGCA", None);
+/// let r2 = SequenceRecord::new_owned("r", "TGACTGCA", None);
+/// let merged = merge_pair(&r1, &r2, 4, 0).unwrap();
+/// assert_eq!(&merged.seq[..], b"ACGTTGCAGTCA");
+/// ```
+pub fn merge_pair(
+    r1: &SequenceRecord,
+    r2: &SequenceRecord,
+    min_overlap: usize,
+    max_mismatch: usize,
+) -> Option<OwnedSequenceRecord> {
+    let r2_seq = r2.seq.reverse_complement();
+    let r2_qual: Option<Vec<u8>> = r2.qual.as_ref().map(|q| q.iter().rev().cloned().collect());
+    let max_overlap = r1.seq.len().min(r2_seq.len());
+
+    // the best overlap so far and its number of mismatches
+    let mut best: Option<(usize, usize)> = None;
+    for overlap in min_overlap.max(1)..=max_overlap {
+        let mismatches = r1.seq[r1.seq.len() - overlap..].count_differences_to(&r2_seq[..overlap]);
+        if mismatches > max_mismatch {
+            continue;
+        }
+        match best {
+            // compare the mismatch fractions without dividing
+            Some((o, m)) if mismatches * o > m * overlap => {}
+            _ => best = Some((overlap, mismatches)),
+        }
+    }
+    let (overlap, _) = best?;
+
+    let start = r1.seq.len() - overlap;
+    let mut seq = r1.seq[..start].to_vec();
+    let mut qual = match (&r1.qual, &r2_qual) {
+        (Some(q1), Some(_)) => Some(q1[..start].to_vec()),
+        _ => None,
+    };
+    for i in 0..overlap {
+        let (b1, b2) = (r1.seq[start + i], r2_seq[i]);
+        let q1 = r1.qual.as_ref().map_or(0, |q| q[start + i]);
+        let q2 = r2_qual.as_ref().map_or(0, |q| q[i]);
+        seq.push(if q2 > q1 { b2 } else { b1 });
+        if let Some(qual) = &mut qual {
+            qual.push(q1.max(q2));
+        }
+    }
+    seq.extend_from_slice(&r2_seq[overlap..]);
+    if let (Some(qual), Some(q2)) = (&mut qual, &r2_qual) {
+        qual.extend_from_slice(&q2[overlap..]);
+    }
+    Some(SequenceRecord::new_owned(r1.id.to_vec(), seq, qual))
+}
+
+/// The k-mer size `check_pair_orientation` compares reads with
+const ORIENTATION_K: usize = 15;
+
+/// Which way the two reads of a pair point relative to each other
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PairOrientation {
+    /// Forward-reverse: the reads point towards each other (e.g. standard
+    /// Illumina paired-end reads)
+    FR,
+    /// Reverse-forward: the reads point away from each other (e.g. some
+    /// mate-pair libraries)
+    RF,
+    /// Both reads are on the same strand
+    FF,
+    /// The reads don't share enough sequence to tell
+    Unknown,
+}
+
+/// Guess the orientation of a read pair from the 15-mers the two reads
+/// share (ignoring case). This only works for pairs that overlap, so e.g.
+/// it's meant for checking a sample of pairs from a library rather than
+/// every pair.
+///
+/// Each 15-mer of R1 that's also in R2 on the same strand is a vote for
+/// `FF`. One that's in the reverse complement of R2 is a vote for `FR` if
+/// it's at least as far along R1 as the reverse complemented R2 (i.e. R1
+/// starts upstream of R2) and `RF` otherwise. The orientation with the
+/// most votes wins; `Unknown` is returned if there aren't any or there's
+/// a tie.
+///
+/// ```
+/// use needletail::sequence_record::{check_pair_orientation, PairOrientation};
+/// use needletail::{Sequence, SequenceRecord};
+///
+/// let fragment = b"ATGCGTACGTTAGCCGATAGGCTTACGATCGATGCAAGTC";
+/// let r1 = SequenceRecord::new_owned("r", &fragment[..30], None);
+/// let r2 = SequenceRecord::new_owned("r", fragment[10..].reverse_complement(), None);
+/// assert_eq!(check_pair_orientation(&r1, &r2), PairOrientation::FR);
+/// ```
+pub fn check_pair_orientation(r1: &SequenceRecord, r2: &SequenceRecord) -> PairOrientation {
+    let k = ORIENTATION_K;
+    let r1_seq = r1.seq.to_ascii_uppercase();
+    let r2_seq = r2.seq.to_ascii_uppercase();
+    let r2_rc = r2_seq.reverse_complement();
+    if r1_seq.len() < k || r2_seq.len() < k {
+        return PairOrientation::Unknown;
+    }
+
+    let mut r1_kmers = HashMap::new();
+    for (pos, kmer) in r1_seq.windows(k).enumerate() {
+        r1_kmers.entry(kmer).or_insert(pos);
+    }
+    let (mut fr, mut rf, mut ff) = (0usize, 0usize, 0usize);
+    for kmer in r2_seq.windows(k) {
+        if r1_kmers.contains_key(kmer) {
+            ff += 1;
+        }
+    }
+    for (pos, kmer) in r2_rc.windows(k).enumerate() {
+        if let Some(r1_pos) = r1_kmers.get(kmer) {
+            if *r1_pos >= pos {
+                fr += 1;
+            } else {
+                rf += 1;
+            }
+        }
+    }
+
+    let most = fr.max(rf).max(ff);
+    let winners = [fr, rf, ff].iter().filter(|v| **v == most).count();
+    if most == 0 || winners > 1 {
+        PairOrientation::Unknown
+    } else if most == fr {
+        PairOrientation::FR
+    } else if most == rf {
+        PairOrientation::RF
+    } else {
+        PairOrientation::FF
+    }
+}
+
+/// A builder for constructing (owned) SequenceRecords programmatically
+/// (e.g. for simulated reads or assembled contigs).
+///
+/// ```
+/// use needletail::sequence_record::SequenceRecordBuilder;
+///
+/// let rec = SequenceRecordBuilder::new("read1")
+///     .seq("ACGT")
+///     .qual("IIII")
+///     .build()
+///     .unwrap();
+/// assert_eq!(&rec.seq[..], b"ACGT");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SequenceRecordBuilder {
+    id: Vec<u8>,
+    seq: Vec<u8>,
+    qual: Option<Vec<u8>>,
+}
+
+impl SequenceRecordBuilder {
+    pub fn new<I: Into<Vec<u8>>>(id: I) -> Self {
+        SequenceRecordBuilder {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn seq<S: Into<Vec<u8>>>(mut self, seq: S) -> Self {
+        self.seq = seq.into();
+        self
+    }
+
+    pub fn qual<Q: Into<Vec<u8>>>(mut self, qual: Q) -> Self {
+        self.qual = Some(qual.into());
+        self
+    }
+
+    /// Build the record, checking that the quality scores (if there are
+    /// any) are the same length as the sequence.
+    pub fn build(self) -> Result<OwnedSequenceRecord, ParseError> {
+        if let Some(qual) = &self.qual {
+            if qual.len() != self.seq.len() {
+                return Err(ParseError::new(
+                    "Sequence and quality lengths differed",
+                    ParseErrorType::InvalidRecord,
+                )
+                .context(String::from_utf8_lossy(&self.id)));
+            }
+        }
+        Ok(SequenceRecord::new_owned(self.id, self.seq, self.qual))
+    }
+}
+
 impl<'a> From<&'a [u8]> for SequenceRecord<'a> {
     fn from(slice: &'a [u8]) -> Self {
         SequenceRecord::new(Cow::from(&b""[..]), slice.into(), None)
     }
 }
 
+impl<'a> SequenceRecord<'a> {
+    /// The parts of the record that comparisons are based on
+    fn key(&self) -> (&[u8], &[u8], Option<&[u8]>) {
+        (&self.id, &self.seq, self.qual.as_deref())
+    }
+}
+
+impl<'a, 'b> PartialEq<SequenceRecord<'b>> for SequenceRecord<'a> {
+    fn eq(&self, other: &SequenceRecord<'b>) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<'a> Eq for SequenceRecord<'a> {}
+
+impl<'a> PartialOrd for SequenceRecord<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for SequenceRecord<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl<'a> Hash for SequenceRecord<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+impl<'a> Deref for SequenceRecord<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.seq
+    }
+}
+
 impl<'a> Sequence<'a> for SequenceRecord<'a> {
     fn sequence(&'a self) -> &'a [u8] {
         self.seq.as_ref()
@@ -116,3 +572,278 @@ impl<'a> QualitySequence<'a> for SequenceRecord<'a> {
         // fake high quality scores? vec![b'I'; self.sequence().len()]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_strings() {
+        let rec = SequenceRecord::new_owned("read1 é", "ACGT", None);
+        assert!(matches!(rec.id_str(), Cow::Borrowed("read1 é")));
+        assert_eq!(rec.seq_str(), "ACGT");
+
+        let rec = SequenceRecord::new_owned(&b"bad\xffid"[..], &b"AC\x80"[..], None);
+        assert_eq!(rec.id_str(), "bad\u{FFFD}id");
+        assert_eq!(rec.seq_str(), "AC\u{FFFD}");
+    }
+
+    #[test]
+    fn test_desc() {
+        let rec = SequenceRecord::new_owned("read1  length=150 x", "A", None);
+        assert_eq!(rec.desc(), Some(&b"length=150 x"[..]));
+        assert_eq!(SequenceRecord::new_owned("read1", "A", None).desc(), None);
+        assert_eq!(
+            SequenceRecord::new_owned("read1 \t", "A", None).desc(),
+            None
+        );
+        assert_eq!(SequenceRecord::new_owned("", "A", None).desc(), None);
+    }
+
+    #[test]
+    fn test_merge_pair() {
+        // the reads overlap by 4 bases in the middle of the fragment
+        let fragment = &b"ACGTTGCATGCCATGAGTCA"[..];
+        let r1 = SequenceRecord::new_owned("r", &fragment[..12], Some(vec![b'I'; 12]));
+        let r2 = SequenceRecord::new_owned(
+            "r",
+            fragment[8..].reverse_complement(),
+            Some(vec![b'5'; 12]),
+        );
+        let merged = merge_pair(&r1, &r2, 4, 0).unwrap();
+        assert_eq!(&merged.seq[..], fragment);
+        assert_eq!(&merged.qual.unwrap()[..], b"IIIIIIIIIIII55555555");
+
+        // a low quality mismatch in R1 is fixed by R2
+        let mut bad_r1 = b"ACGTTGCATGAC".to_vec();
+        let mut bad_qual = vec![b'I'; 12];
+        bad_qual[10] = b'#';
+        bad_r1[10] = b'A';
+        let bad_r1 = SequenceRecord::new_owned("r", bad_r1, Some(bad_qual));
+        assert!(merge_pair(&bad_r1, &r2, 4, 0).is_none());
+        let merged = merge_pair(&bad_r1, &r2, 4, 1).unwrap();
+        assert_eq!(&merged.seq[..], fragment);
+        assert_eq!(merged.qual.unwrap()[10], b'5');
+
+        // reads that don't overlap enough aren't merged
+        let r2 = SequenceRecord::new_owned("r", fragment[10..].reverse_complement(), None);
+        assert!(merge_pair(&r1, &r2, 4, 0).is_none());
+    }
+
+    #[test]
+    fn test_check_pair_orientation() {
+        let mut rng = SplitMix64::new(7);
+        let fragment: Vec<u8> = (0..80)
+            .map(|_| b"ACGT"[(rng.next_u64() % 4) as usize])
+            .collect();
+        let rec = |seq: Vec<u8>| SequenceRecord::new_owned("r", seq, None);
+
+        // R1 from the start and R2 from the other strand at the end
+        let r1 = rec(fragment[..50].to_vec());
+        let r2 = rec(fragment[30..].reverse_complement());
+        assert_eq!(check_pair_orientation(&r1, &r2), PairOrientation::FR);
+        // even when the reads cover the whole fragment
+        let r2 = rec(fragment.reverse_complement());
+        let whole = rec(fragment.to_ascii_lowercase());
+        assert_eq!(check_pair_orientation(&whole, &r2), PairOrientation::FR);
+
+        // pointing away from each other
+        let r1 = rec(fragment[..50].reverse_complement());
+        let r2 = rec(fragment[30..].to_vec());
+        assert_eq!(check_pair_orientation(&r1, &r2), PairOrientation::RF);
+
+        let r1 = rec(fragment[..50].to_vec());
+        let r2 = rec(fragment[30..].to_vec());
+        assert_eq!(check_pair_orientation(&r1, &r2), PairOrientation::FF);
+
+        // no overlap (or too short to have any 15-mers)
+        let r2 = rec(fragment[60..].reverse_complement());
+        assert_eq!(check_pair_orientation(&r1, &r2), PairOrientation::Unknown);
+        let r2 = rec(b"ACGT".to_vec());
+        assert_eq!(check_pair_orientation(&r1, &r2), PairOrientation::Unknown);
+    }
+
+    #[test]
+    fn test_simulate_pair() {
+        let fragment = SequenceRecord::new_owned("frag", "ATGCGTACCGTTAGGCAT", None);
+        let (r1, r2) = fragment.simulate_pair(6, 40);
+        assert_eq!(&r1.id[..], b"frag/1");
+        assert_eq!(&r1.seq[..], b"ATGCGT");
+        // the last 6 bases are TAGGCAT's AGGCAT, reverse complemented
+        assert_eq!(&r2.seq[..], b"ATGCCT");
+        assert_eq!(&r2.seq[..], &b"AGGCAT".reverse_complement()[..]);
+        assert_eq!(r1.qual.as_deref(), Some(&b"IIIIII"[..]));
+        assert_eq!(r2.qual.as_deref(), Some(&b"IIIIII"[..]));
+
+        // reads longer than the fragment are cut down to it
+        let (r1, r2) = fragment.simulate_pair(100, 2);
+        assert_eq!(r1.seq, fragment.seq);
+        assert_eq!(&r2.seq[..], &fragment.reverse_complement()[..]);
+        assert_eq!(r2.qual.unwrap().len(), fragment.seq.len());
+
+        // scores too high for FASTQ are capped
+        let (r1, _) = fragment.simulate_pair(2, 255);
+        assert_eq!(r1.qual.as_deref(), Some(&b"~~"[..]));
+    }
+
+    #[test]
+    fn test_subsample_bases() {
+        let seq = b"ACGT".repeat(2500);
+        // quality scores that say which base they go with
+        let qual: Vec<u8> = seq.iter().map(|b| b - 32).collect();
+        let rec = SequenceRecord::new_owned("read", seq, Some(qual));
+
+        let sub = rec.subsample_bases(0.3, 7);
+        assert!(sub.seq.len() > 2800 && sub.seq.len() < 3200);
+        let qual = sub.qual.as_ref().unwrap();
+        assert_eq!(qual.len(), sub.seq.len());
+        assert!(sub.seq.iter().zip(qual.iter()).all(|(b, q)| *q == b - 32));
+
+        assert_eq!(rec.subsample_bases(0.3, 7), sub);
+        assert_ne!(rec.subsample_bases(0.3, 8), sub);
+    }
+
+    #[test]
+    fn test_record_ordering() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let mut recs = [
+            SequenceRecord::new_owned("b", "AC", None),
+            SequenceRecord::new_owned("a", "GT", Some(b"II".to_vec())),
+            SequenceRecord::new_owned("a", "GT", None),
+            SequenceRecord::new_owned("a", "CC", None),
+        ];
+        recs.sort();
+        let order: Vec<_> = recs.iter().map(|r| r.key()).collect();
+        assert_eq!(
+            order,
+            vec![
+                (&b"a"[..], &b"CC"[..], None),
+                (b"a", b"GT", None),
+                (b"a", b"GT", Some(&b"II"[..])),
+                (b"b", b"AC", None)
+            ]
+        );
+
+        // borrowed and owned data with different checksums are still equal
+        let mut borrowed = SequenceRecord::new(Cow::from(&b"b"[..]), Cow::from(&b"AC"[..]), None);
+        borrowed.checksum = Some(1);
+        assert_eq!(borrowed, recs[3]);
+        assert_ne!(recs[1], recs[2]);
+
+        let set: HashSet<_> = recs.iter().chain(Some(&borrowed)).collect();
+        assert_eq!(set.len(), 4);
+        let mut set = BTreeSet::new();
+        assert!(set.insert(borrowed));
+        assert!(!set.insert(SequenceRecord::new_owned("b", "AC", None)));
+    }
+
+    #[test]
+    fn test_builder() {
+        let rec = SequenceRecordBuilder::new("read1 simulated")
+            .seq(b"ACGT".to_vec())
+            .qual("II#I")
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        rec.write_fastq(&mut out, b"\n").unwrap();
+        assert_eq!(&out[..], b"@read1 simulated\nACGT\n+\nII#I\n");
+
+        let rec = SequenceRecordBuilder::new("contig")
+            .seq("ACGT")
+            .build()
+            .unwrap();
+        assert_eq!(rec.qual, None);
+
+        let e = SequenceRecordBuilder::new("bad")
+            .seq("ACGT")
+            .qual("III")
+            .build()
+            .unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::InvalidRecord);
+        assert_eq!(e.context, "bad");
+    }
+
+    #[test]
+    fn test_deref() {
+        let rec = SequenceRecord::new(b"test".to_vec().into(), b"ACGTN".to_vec().into(), None);
+        assert_eq!(rec.len(), 5);
+        assert!(!rec.is_empty());
+        assert_eq!(rec.iter().filter(|b| **b == b'N').count(), 1);
+        assert_eq!(&rec[1..3], b"CG");
+        // trait methods still work on the record itself
+        assert_eq!(rec.reverse_complement(), b"NACGT");
+
+        let rec = SequenceRecord::new_owned("empty", "", None);
+        assert!(rec.is_empty());
+    }
+
+    #[test]
+    fn test_reverse() {
+        let rec = SequenceRecord::new_owned("read", "AACGT", Some(b"#!III".to_vec()));
+        let rev = rec.reverse();
+        assert_eq!(&rev.id[..], b"read");
+        assert_eq!(&rev.seq[..], b"TGCAA");
+        assert_eq!(rev.qual.as_deref(), Some(&b"III!#"[..]));
+        assert_eq!(rev.seq.len(), rev.qual.unwrap().len());
+
+        let rec = SequenceRecord::new_owned("contig", "ACG", None);
+        assert_eq!(rec.reverse().qual, None);
+    }
+
+    #[test]
+    fn test_reverse_complement_record() {
+        use crate::formats::parse_sequence_reader;
+
+        let mut rc = None;
+        parse_sequence_reader(
+            &b"@r1 x\nAACGTN\n+\n#!ABCI\n"[..],
+            |_| {},
+            |rec| rc = Some(rec.reverse_complement_record()),
+        )
+        .unwrap();
+        let rc = rc.unwrap();
+        assert_eq!(&rc.id[..], b"r1 x");
+        assert_eq!(&rc.seq[..], b"NACGTT");
+        assert_eq!(rc.qual.as_deref(), Some(&b"ICBA!#"[..]));
+        // flipping it back gets the original
+        let back = rc.reverse_complement_record();
+        assert_eq!(&back.seq[..], b"AACGTN");
+        assert_eq!(back.qual.as_deref(), Some(&b"#!ABCI"[..]));
+
+        let rec = SequenceRecord::new_owned("contig", "ACG", None);
+        assert_eq!(rec.reverse_complement_record().qual, None);
+    }
+
+    #[test]
+    fn test_fix_length() {
+        let rec = SequenceRecord::new_owned("read", "ACGTACGT", Some(b"IIII!!!!".to_vec()));
+        let fixed = rec.fix_length(5, b'N', 0);
+        assert_eq!(&fixed.id[..], b"read");
+        assert_eq!(&fixed.seq[..], b"ACGTA");
+        assert_eq!(&fixed.qual.unwrap()[..], b"IIII!");
+
+        let fixed = rec.fix_length(10, b'A', 40);
+        assert_eq!(&fixed.seq[..], b"ACGTACGTAA");
+        assert_eq!(&fixed.qual.unwrap()[..], b"IIII!!!!II");
+
+        let fixed = rec.fix_length(9, b'N', 230);
+        assert_eq!(&fixed.qual.unwrap()[..], b"IIII!!!!~");
+
+        let fixed = rec.fix_length(8, b'N', 0);
+        assert_eq!(fixed.seq, rec.seq);
+        assert_eq!(fixed.qual, rec.qual);
+
+        let rec = SequenceRecord::new_owned("contig", "AC", None);
+        let fixed = rec.fix_length(4, b'N', 0);
+        assert_eq!(&fixed.seq[..], b"ACNN");
+        assert_eq!(fixed.qual, None);
+    }
+
+    #[test]
+    fn test_new_owned() {
+        let rec: OwnedSequenceRecord = SequenceRecord::new_owned("a", "AC\nGT", None);
+        // unlike parsing, nothing is stripped out
+        assert_eq!(&rec.seq[..], b"AC\nGT");
+    }
+}
@@ -0,0 +1,511 @@
+//! Summary statistics computed across sets of sequences.
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::formats::parse_sequence_reader;
+use crate::util::{ParseError, ParseErrorType};
+
+/// The index of a base in a column of a `PositionMatrix` (A, C, G, T and
+/// then anything else).
+#[inline]
+fn base_index(base: u8) -> usize {
+    match base {
+        b'A' | b'a' => 0,
+        b'C' | b'c' => 1,
+        b'G' | b'g' => 2,
+        b'T' | b't' | b'U' | b'u' => 3,
+        _ => 4,
+    }
+}
+
+/// How `PositionMatrix::consensus` should handle columns where more than
+/// one base is the most common
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TieBreak {
+    /// Use the first of the tied bases (in the order A, C, G, T)
+    First,
+    /// Use an N
+    N,
+    /// Use the IUPAC ambiguity code for the tied bases (e.g. R for A/G)
+    Ambiguous,
+}
+
+/// IUPAC codes for every combination of ACGT; indexed by a bitmask where
+/// A is the lowest bit (so e.g. A and G is 0b0101)
+const IUPAC_CODES: &[u8; 16] = b"NACMGRSVTWYHKDBN";
+
+/// Per-position base counts across a set of aligned, equal-length sequences
+/// (e.g. for building position weight matrices or consensus sequences).
+///
+/// ```
+/// use needletail::stats::PositionMatrix;
+///
+/// let mut matrix = PositionMatrix::new(2);
+/// matrix.add(b"AC").unwrap();
+/// matrix.add(b"AG").unwrap();
+/// assert_eq!(matrix.counts()[1], [0, 1, 1, 0, 0]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionMatrix {
+    counts: Vec<[u32; 5]>,
+    n_seqs: usize,
+}
+
+impl PositionMatrix {
+    /// Create an empty matrix for sequences `width` bases long
+    pub fn new(width: usize) -> Self {
+        PositionMatrix {
+            counts: vec![[0; 5]; width],
+            n_seqs: 0,
+        }
+    }
+
+    /// The length of the sequences in the matrix
+    pub fn width(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// The number of sequences that have been added
+    pub fn n_seqs(&self) -> usize {
+        self.n_seqs
+    }
+
+    /// Tally the bases in `seq` (ignoring case and treating U as T). Errors
+    /// if the sequence isn't the same length as the matrix.
+    pub fn add(&mut self, seq: &[u8]) -> Result<(), ParseError> {
+        if seq.len() != self.width() {
+            return Err(ParseError::new(
+                format!(
+                    "Sequence length ({}) differs from the matrix width ({})",
+                    seq.len(),
+                    self.width()
+                ),
+                ParseErrorType::Invalid,
+            )
+            .record(self.n_seqs + 1));
+        }
+        for (column, base) in self.counts.iter_mut().zip(seq) {
+            column[base_index(*base)] += 1;
+        }
+        self.n_seqs += 1;
+        Ok(())
+    }
+
+    /// The counts of each base in each column in the order A, C, G, T and
+    /// then everything else (e.g. N's and gaps).
+    pub fn counts(&self) -> &[[u32; 5]] {
+        &self.counts
+    }
+
+    /// The frequency of each of A, C, G and T in each column (ignoring
+    /// other characters). Columns without any ACGTs are all NaN.
+    pub fn frequencies(&self) -> Vec<[f64; 4]> {
+        self.counts
+            .iter()
+            .map(|column| {
+                let total = f64::from(column[..4].iter().sum::<u32>());
+                let mut freqs = [0.; 4];
+                for (freq, count) in freqs.iter_mut().zip(column) {
+                    *freq = f64::from(*count) / total;
+                }
+                freqs
+            })
+            .collect()
+    }
+
+    /// The most common base (A, C, G or T) in each column; columns without
+    /// any of those are N's. Ties are broken according to `tie_break`.
+    ///
+    /// ```
+    /// use needletail::stats::{PositionMatrix, TieBreak};
+    ///
+    /// let mut matrix = PositionMatrix::new(3);
+    /// matrix.add(b"ACN").unwrap();
+    /// matrix.add(b"AGN").unwrap();
+    /// assert_eq!(matrix.consensus(TieBreak::Ambiguous), b"ASN");
+    /// ```
+    pub fn consensus(&self, tie_break: TieBreak) -> Vec<u8> {
+        self.counts
+            .iter()
+            .map(|column| {
+                let max = *column[..4].iter().max().unwrap();
+                if max == 0 {
+                    return b'N';
+                }
+                let mut mask: usize = 0;
+                for (i, count) in column[..4].iter().enumerate() {
+                    if *count == max {
+                        mask |= 1 << i;
+                    }
+                }
+                match tie_break {
+                    _ if mask.count_ones() == 1 => IUPAC_CODES[mask],
+                    TieBreak::First => IUPAC_CODES[mask & mask.wrapping_neg()],
+                    TieBreak::N => b'N',
+                    TieBreak::Ambiguous => IUPAC_CODES[mask],
+                }
+            })
+            .collect()
+    }
+}
+
+/// Tracks the longest and shortest records seen (and their ids) in a single
+/// pass, e.g. from inside a parsing callback.
+///
+/// ```
+/// use needletail::stats::LengthExtremes;
+///
+/// let mut extremes = LengthExtremes::new();
+/// extremes.add(b"a", 10);
+/// extremes.add(b"b", 3);
+/// let (longest, shortest) = extremes.finalize();
+/// assert_eq!(longest, Some((b"a".to_vec(), 10)));
+/// assert_eq!(shortest, Some((b"b".to_vec(), 3)));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LengthExtremes {
+    longest: Option<IdLength>,
+    shortest: Option<IdLength>,
+}
+
+/// A record's id and its length
+pub type IdLength = (Vec<u8>, usize);
+
+impl LengthExtremes {
+    pub fn new() -> Self {
+        LengthExtremes::default()
+    }
+
+    /// Add a record; if it ties with the current longest or shortest, the
+    /// record that was added first is kept.
+    pub fn add(&mut self, id: &[u8], len: usize) {
+        match &self.longest {
+            Some((_, l)) if *l >= len => {}
+            _ => self.longest = Some((id.to_vec(), len)),
+        }
+        match &self.shortest {
+            Some((_, l)) if *l <= len => {}
+            _ => self.shortest = Some((id.to_vec(), len)),
+        }
+    }
+
+    /// Returns the (id, length) of the longest and then the shortest
+    /// records (or `None`s if nothing was added).
+    pub fn finalize(self) -> (Option<IdLength>, Option<IdLength>) {
+        (self.longest, self.shortest)
+    }
+}
+
+/// Tracks the most common read length (e.g. to find the read length of
+/// fixed-length short-read data that's had a few reads trimmed).
+///
+/// ```
+/// use needletail::stats::ReadLengthMode;
+///
+/// let mut lengths = ReadLengthMode::new();
+/// for len in &[150, 150, 149, 150, 75] {
+///     lengths.add(*len);
+/// }
+/// assert_eq!(lengths.mode(), Some(150));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReadLengthMode {
+    counts: HashMap<usize, u64>,
+}
+
+impl ReadLengthMode {
+    pub fn new() -> Self {
+        ReadLengthMode::default()
+    }
+
+    /// Add the length of a read
+    pub fn add(&mut self, len: usize) {
+        *self.counts.entry(len).or_insert(0) += 1;
+    }
+
+    /// The most common length and how many reads had it; if several lengths
+    /// are equally common the shortest is returned.
+    pub fn mode_count(&self) -> Option<(usize, u64)> {
+        self.counts
+            .iter()
+            .map(|(len, n)| (*len, *n))
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+    }
+
+    /// The most common length (`None` if nothing was added)
+    pub fn mode(&self) -> Option<usize> {
+        self.mode_count().map(|(len, _)| len)
+    }
+}
+
+/// How `percent_identity` should treat positions with a gap (`-`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GapHandling {
+    /// A gap in one sequence counts as a mismatch
+    Mismatch,
+    /// Any position with a gap in either sequence is left out entirely
+    Exclude,
+}
+
+/// The percentage of positions that are the same (ignoring case) in two
+/// already aligned sequences. Positions with a gap in both sequences are
+/// always left out; positions with a gap in only one are handled according
+/// to `gaps`. Returns NaN if there are no positions to compare.
+///
+/// ```
+/// use needletail::stats::{percent_identity, GapHandling};
+///
+/// let identity = percent_identity(b"ACGT", b"ACGA", GapHandling::Exclude).unwrap();
+/// assert_eq!(identity, 75.);
+/// ```
+pub fn percent_identity(a: &[u8], b: &[u8], gaps: GapHandling) -> Result<f64, ParseError> {
+    if a.len() != b.len() {
+        return Err(ParseError::new(
+            format!(
+                "Aligned sequences must be the same length ({} and {})",
+                a.len(),
+                b.len()
+            ),
+            ParseErrorType::Invalid,
+        ));
+    }
+    let (mut matches, mut total) = (0usize, 0usize);
+    for (x, y) in a.iter().zip(b) {
+        match (*x == b'-', *y == b'-') {
+            (true, true) => continue,
+            (true, false) | (false, true) if gaps == GapHandling::Exclude => continue,
+            _ => {}
+        }
+        total += 1;
+        if x.eq_ignore_ascii_case(y) {
+            matches += 1;
+        }
+    }
+    if total == 0 {
+        return Ok(f64::NAN);
+    }
+    Ok(100. * matches as f64 / total as f64)
+}
+
+/// Summary statistics for a whole file (see `compute_stats`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeqStats {
+    pub n_records: usize,
+    pub total_bases: usize,
+    pub min_len: usize,
+    pub max_len: usize,
+    pub mean_len: f64,
+    /// The length of the shortest record in the smallest set of records
+    /// that makes up half of all the bases
+    pub n50: usize,
+    /// The fraction of the A, C, G and T (or U) bases that are G or C
+    pub gc_content: f64,
+    /// The mean Phred score over every base (only for FASTQ files)
+    pub mean_quality: Option<f64>,
+}
+
+/// Compute `seqkit stats`-like summary statistics for a FASTA or FASTQ
+/// stream in a single pass. All of the record lengths are kept in memory to
+/// find the N50. Averages are NaN if there aren't any records (or bases).
+///
+/// ```
+/// use needletail::stats::compute_stats;
+///
+/// let stats = compute_stats(&b">a\nACGG\n>b\nAT\n"[..]).unwrap();
+/// assert_eq!(stats.total_bases, 6);
+/// assert_eq!(stats.gc_content, 0.5);
+/// ```
+pub fn compute_stats<R: Read>(reader: R) -> Result<SeqStats, ParseError> {
+    let mut lengths = Vec::new();
+    let (mut gc, mut acgt) = (0usize, 0usize);
+    let (mut qual_total, mut n_qual) = (0u64, 0usize);
+    let mut is_fastq = false;
+    parse_sequence_reader(
+        reader,
+        |file_type| is_fastq = file_type == "FASTQ",
+        |rec| {
+            lengths.push(rec.seq.len());
+            for base in rec.seq.iter() {
+                match base {
+                    b'G' | b'g' | b'C' | b'c' => {
+                        gc += 1;
+                        acgt += 1;
+                    }
+                    b'A' | b'a' | b'T' | b't' | b'U' | b'u' => acgt += 1,
+                    _ => {}
+                }
+            }
+            if let Some(qual) = &rec.qual {
+                qual_total += qual
+                    .iter()
+                    .map(|q| u64::from(q.saturating_sub(33)))
+                    .sum::<u64>();
+                n_qual += qual.len();
+            }
+        },
+    )?;
+
+    lengths.sort_unstable_by(|a, b| b.cmp(a));
+    let total_bases: usize = lengths.iter().sum();
+    let mut n50 = 0;
+    let mut cumulative = 0;
+    for len in &lengths {
+        cumulative += len;
+        if 2 * cumulative >= total_bases {
+            n50 = *len;
+            break;
+        }
+    }
+
+    Ok(SeqStats {
+        n_records: lengths.len(),
+        total_bases,
+        min_len: lengths.last().copied().unwrap_or(0),
+        max_len: lengths.first().copied().unwrap_or(0),
+        mean_len: total_bases as f64 / lengths.len() as f64,
+        n50,
+        gc_content: gc as f64 / acgt as f64,
+        mean_quality: if is_fastq {
+            Some(qual_total as f64 / n_qual as f64)
+        } else {
+            None
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_matrix() {
+        let mut matrix = PositionMatrix::new(4);
+        matrix.add(b"ACGT").unwrap();
+        matrix.add(b"AcGN").unwrap();
+        matrix.add(b"ATGu").unwrap();
+        assert_eq!(matrix.n_seqs(), 3);
+        assert_eq!(
+            matrix.counts(),
+            &[
+                [3, 0, 0, 0, 0],
+                [0, 2, 0, 1, 0],
+                [0, 0, 3, 0, 0],
+                [0, 0, 0, 2, 1]
+            ]
+        );
+
+        let freqs = matrix.frequencies();
+        assert_eq!(freqs[0], [1., 0., 0., 0.]);
+        assert_eq!(freqs[1], [0., 2. / 3., 0., 1. / 3.]);
+        assert_eq!(freqs[3], [0., 0., 0., 1.]);
+
+        let e = matrix.add(b"ACG").unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::Invalid);
+        assert_eq!(e.record, 4);
+        assert_eq!(matrix.n_seqs(), 3);
+
+        let mut matrix = PositionMatrix::new(1);
+        matrix.add(b"-").unwrap();
+        assert!(matrix.frequencies()[0][0].is_nan());
+    }
+
+    #[test]
+    fn test_consensus() {
+        let mut matrix = PositionMatrix::new(5);
+        matrix.add(b"AAGTN").unwrap();
+        matrix.add(b"ACGCN").unwrap();
+        matrix.add(b"ACTGN").unwrap();
+        matrix.add(b"AGTAN").unwrap();
+        // A clear majority; a clear (C) majority; a G/T tie; a four way tie;
+        // and nothing
+        assert_eq!(matrix.consensus(TieBreak::First), b"ACGAN");
+        assert_eq!(matrix.consensus(TieBreak::N), b"ACNNN");
+        assert_eq!(matrix.consensus(TieBreak::Ambiguous), b"ACKNN");
+
+        let mut matrix = PositionMatrix::new(3);
+        matrix.add(b"ACA").unwrap();
+        matrix.add(b"CGT").unwrap();
+        matrix.add(b"TTG").unwrap();
+        assert_eq!(matrix.consensus(TieBreak::First), b"ACA");
+        assert_eq!(matrix.consensus(TieBreak::Ambiguous), b"HBD");
+    }
+
+    #[test]
+    fn test_length_extremes() {
+        use crate::formats::parse_sequence_reader;
+        use std::io::Cursor;
+
+        let mut extremes = LengthExtremes::new();
+        let data = b">a\nACGT\n>b\nACGTAC\n>c\nAC\n>d\nACGTAC\n>e\nAC\n";
+        parse_sequence_reader(
+            Cursor::new(&data[..]),
+            |_| {},
+            |rec| extremes.add(&rec.id, rec.seq.len()),
+        )
+        .unwrap();
+        // ties go to whichever record came first
+        let (longest, shortest) = extremes.finalize();
+        assert_eq!(longest, Some((b"b".to_vec(), 6)));
+        assert_eq!(shortest, Some((b"c".to_vec(), 2)));
+
+        assert_eq!(LengthExtremes::new().finalize(), (None, None));
+    }
+
+    #[test]
+    fn test_percent_identity() {
+        let identity = percent_identity(b"ACGTACGT", b"acgtacgt", GapHandling::Mismatch);
+        assert_eq!(identity, Ok(100.));
+        let identity = percent_identity(b"ACGTACGT", b"ACGTACGA", GapHandling::Mismatch);
+        assert_eq!(identity, Ok(87.5));
+
+        // one gap in each sequence and one gap in both
+        let (a, b) = (b"AC-TAC-T", b"ACGTA--T");
+        assert_eq!(
+            percent_identity(a, b, GapHandling::Mismatch),
+            Ok(100. * 5. / 7.)
+        );
+        assert_eq!(percent_identity(a, b, GapHandling::Exclude), Ok(100.));
+
+        assert!(percent_identity(b"--", b"--", GapHandling::Exclude)
+            .unwrap()
+            .is_nan());
+        let e = percent_identity(b"ACGT", b"ACG", GapHandling::Exclude).unwrap_err();
+        assert_eq!(e.error_type, ParseErrorType::Invalid);
+    }
+
+    #[test]
+    fn test_read_length_mode() {
+        let mut lengths = ReadLengthMode::new();
+        assert_eq!(lengths.mode(), None);
+        for len in &[100, 98, 100, 35, 100, 98, 151] {
+            lengths.add(*len);
+        }
+        assert_eq!(lengths.mode_count(), Some((100, 3)));
+
+        // ties go to the shorter length
+        lengths.add(98);
+        assert_eq!(lengths.mode_count(), Some((98, 3)));
+    }
+
+    #[test]
+    fn test_compute_stats() {
+        let fasta = b">a\nACGTACGTAC\n>b\nGGGG\nCCCC\n>c\nNNNNN\n>d\nATA\n>e\nAT\n";
+        let stats = compute_stats(&fasta[..]).unwrap();
+        assert_eq!(stats.n_records, 5);
+        assert_eq!(stats.total_bases, 28);
+        assert_eq!((stats.min_len, stats.max_len), (2, 10));
+        assert_eq!(stats.mean_len, 5.6);
+        assert_eq!(stats.n50, 8);
+        assert_eq!(stats.gc_content, 13. / 23.);
+        assert_eq!(stats.mean_quality, None);
+
+        let fastq = b"@a\nACG\n+\nII5\n@b\nT\n+\n5\n";
+        let stats = compute_stats(&fastq[..]).unwrap();
+        assert_eq!(stats.total_bases, 4);
+        assert_eq!(stats.mean_quality, Some(30.));
+
+        let stats = compute_stats(&b""[..]).unwrap();
+        assert_eq!((stats.n_records, stats.total_bases, stats.n50), (0, 0, 0));
+        assert!(stats.mean_len.is_nan());
+    }
+}
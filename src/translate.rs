@@ -0,0 +1,144 @@
+//! Translating nucleotide sequences into amino acids.
+
+/// The NCBI standard genetic code (translation table 1), in the order NCBI
+/// lists codons in (TTT, TTC, TTA, TTG, TCT, ... GGG)
+const STANDARD_CODE: &[u8; 64] =
+    b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+
+/// The index of a base in NCBI's codon ordering (T, C, A, G)
+#[inline]
+fn ncbi_index(base: u8) -> Option<usize> {
+    match base {
+        b'T' | b't' | b'U' | b'u' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'A' | b'a' => Some(2),
+        b'G' | b'g' => Some(3),
+        _ => None,
+    }
+}
+
+/// A genetic code mapping each of the 64 codons to an amino acid (with `*`
+/// for stop codons).
+///
+/// ```
+/// use needletail::translate::CodonTable;
+///
+/// let table = CodonTable::standard();
+/// assert_eq!(table.translate_codon(b"ATG"), b'M');
+/// assert_eq!(table.translate_codon(b"uaa"), b'*');
+/// assert_eq!(table.translate_codon(b"ANG"), b'X');
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodonTable {
+    amino_acids: [u8; 64],
+}
+
+impl CodonTable {
+    /// Build a table from the 64 amino acids of a genetic code in NCBI's
+    /// codon order (e.g. the `AAs` line of one of NCBI's translation tables)
+    pub fn from_ncbi(amino_acids: &[u8; 64]) -> Self {
+        CodonTable {
+            amino_acids: *amino_acids,
+        }
+    }
+
+    /// The standard genetic code (NCBI translation table 1)
+    pub fn standard() -> Self {
+        CodonTable::from_ncbi(STANDARD_CODE)
+    }
+
+    /// Translate a single codon (ignoring case and treating U as T). Codons
+    /// with anything other than A, C, G or T in them translate to `X`.
+    ///
+    /// # Panics
+    ///
+    /// If `codon` isn't three bases long.
+    pub fn translate_codon(&self, codon: &[u8]) -> u8 {
+        assert_eq!(codon.len(), 3, "codons must be three bases long");
+        let mut idx = 0;
+        for base in codon {
+            match ncbi_index(*base) {
+                Some(i) => idx = (idx << 2) + i,
+                None => return b'X',
+            }
+        }
+        self.amino_acids[idx]
+    }
+}
+
+/// An open reading frame: a start codon (one that translates to `M`) up to
+/// and including the next in-frame stop codon (see `Sequence::longest_orf`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orf {
+    /// Where the ORF starts and (exclusive) ends in the sequence. For ORFs
+    /// on the reverse strand these are still forward strand coordinates, so
+    /// the ORF is the reverse complement of `seq[start..end]`.
+    pub start: usize,
+    pub end: usize,
+    /// The reading frame (0, 1 or 2) of the strand the ORF's on
+    pub frame: u8,
+    /// Whether the ORF's on the reverse strand
+    pub reverse: bool,
+}
+
+impl Orf {
+    /// The length of the ORF in bases (including the stop codon)
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// The `(start, end, frame)` of the longest ORF on the forward strand of
+/// `seq` (the first one found if there's a tie)
+pub(crate) fn longest_forward_orf(seq: &[u8], table: &CodonTable) -> Option<(usize, usize, u8)> {
+    let mut longest: Option<(usize, usize, u8)> = None;
+    for frame in 0..3 {
+        let mut start = None;
+        for (i, codon) in seq.get(frame..).unwrap_or(b"").chunks_exact(3).enumerate() {
+            let pos = frame + 3 * i;
+            match (table.translate_codon(codon), start) {
+                (b'M', None) => start = Some(pos),
+                (b'*', Some(s)) => {
+                    if longest.is_none_or(|(ls, le, _)| pos + 3 - s > le - ls) {
+                        longest = Some((s, pos + 3, frame as u8));
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    longest
+}
+
+impl Default for CodonTable {
+    fn default() -> Self {
+        CodonTable::standard()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CodonTable;
+
+    #[test]
+    fn test_translate_codon() {
+        let table = CodonTable::standard();
+        assert_eq!(table.translate_codon(b"TTT"), b'F');
+        assert_eq!(table.translate_codon(b"GGG"), b'G');
+        assert_eq!(table.translate_codon(b"TGA"), b'*');
+        assert_eq!(table.translate_codon(b"tgg"), b'W');
+        assert_eq!(table.translate_codon(b"AUG"), b'M');
+        assert_eq!(table.translate_codon(b"GCN"), b'X');
+
+        // vertebrate mitochondrial code, where TGA is W
+        let mito = CodonTable::from_ncbi(
+            b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        );
+        assert_eq!(mito.translate_codon(b"TGA"), b'W');
+    }
+}
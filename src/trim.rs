@@ -0,0 +1,137 @@
+//! Options for trimming reads (see `QualitySequence::trim`).
+
+/// Which trims `QualitySequence::trim` should do. They're always done in
+/// the same order: quality trimming, then adapter removal and then poly-A
+/// trimming.
+///
+/// ```
+/// use needletail::trim::TrimOptions;
+///
+/// let options = TrimOptions::new()
+///     .quality_cutoff(Some(20))
+///     .adapter(Some(b"CTGTCTCTTATA".to_vec()), 3, 1)
+///     .poly_a(Some(10));
+/// assert_eq!(options.quality_cutoff, Some(20));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrimOptions {
+    /// Trim low quality bases off both ends of the read (as Phred scores;
+    /// see `quality_trim`)
+    pub quality_cutoff: Option<u8>,
+    /// Cut an adapter (or the start of one) off the 3' end of the read
+    /// (see `Sequence::find_adapter`)
+    pub adapter: Option<Vec<u8>>,
+    /// How many bases of the adapter have to overlap the read
+    pub adapter_min_overlap: usize,
+    /// How many mismatches are allowed in the adapter
+    pub adapter_max_mismatch: usize,
+    /// Trim a run of at least this many A's off the 3' end of the read
+    pub poly_a: Option<usize>,
+}
+
+impl TrimOptions {
+    pub fn new() -> Self {
+        TrimOptions::default()
+    }
+
+    pub fn quality_cutoff(mut self, cutoff: Option<u8>) -> Self {
+        self.quality_cutoff = cutoff;
+        self
+    }
+
+    pub fn adapter(
+        mut self,
+        adapter: Option<Vec<u8>>,
+        min_overlap: usize,
+        max_mismatch: usize,
+    ) -> Self {
+        self.adapter = adapter;
+        self.adapter_min_overlap = min_overlap;
+        self.adapter_max_mismatch = max_mismatch;
+        self
+    }
+
+    pub fn poly_a(mut self, min_len: Option<usize>) -> Self {
+        self.poly_a = min_len;
+        self
+    }
+}
+
+/// Why part of a read was trimmed off
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrimReason {
+    Quality,
+    Adapter,
+    PolyA,
+}
+
+/// How much one trim cut off each end of a read (see
+/// `QualitySequence::trim_annotations`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrimAnnotation {
+    /// The number of bases cut off the 5' end
+    pub five_prime: usize,
+    /// The number of bases cut off the 3' end
+    pub three_prime: usize,
+    pub reason: TrimReason,
+}
+
+impl TrimAnnotation {
+    pub fn new(five_prime: usize, three_prime: usize, reason: TrimReason) -> Self {
+        TrimAnnotation {
+            five_prime,
+            three_prime,
+            reason,
+        }
+    }
+}
+
+/// Returns the `(start, end)` range of (Phred+33) quality scores left after
+/// trimming low quality bases off both ends with the algorithm BWA and
+/// cutadapt use: the end is cut where the sum of `cutoff - score` from that
+/// end is highest, so an occasional good base in a bad stretch doesn't stop
+/// the trimming.
+///
+/// ```
+/// use needletail::trim::quality_trim;
+///
+/// assert_eq!(quality_trim(b"#IIII5I#I##", 20), (1, 9));
+/// ```
+pub fn quality_trim(qual: &[u8], cutoff: u8) -> (usize, usize) {
+    // how far in from the end the cut should be
+    let cut = |scores: &mut dyn Iterator<Item = &u8>| {
+        let (mut sum, mut best, mut cut) = (0i64, 0i64, 0);
+        for (i, q) in scores.enumerate() {
+            sum += i64::from(cutoff) - i64::from(q.saturating_sub(33));
+            if sum < 0 {
+                break;
+            }
+            if sum > best {
+                best = sum;
+                cut = i + 1;
+            }
+        }
+        cut
+    };
+    let start = cut(&mut qual.iter());
+    if start == qual.len() {
+        return (0, 0);
+    }
+    let end = qual.len() - cut(&mut qual[start..].iter().rev());
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quality_trim;
+
+    #[test]
+    fn test_quality_trim() {
+        // the 5 in the middle of the bad run at the end doesn't stop it
+        assert_eq!(quality_trim(b"IIIIIII#5##", 20), (0, 7));
+        assert_eq!(quality_trim(b"##IIII", 20), (2, 6));
+        assert_eq!(quality_trim(b"IIII", 20), (0, 4));
+        assert_eq!(quality_trim(b"####", 20), (0, 0));
+        assert_eq!(quality_trim(b"", 20), (0, 0));
+    }
+}
@@ -16,6 +16,14 @@ pub enum ParseErrorType {
     IOError,
     /// A generic error occured
     Invalid,
+    /// Paired inputs had differing numbers of records
+    PairLengthMismatch,
+    /// A record's id was the same as a previous record's
+    DuplicateId,
+    /// A record was longer than the maximum allowed
+    RecordTooLarge,
+    /// A record's quality scores had a byte that isn't printable ASCII
+    InvalidQuality,
 }
 
 /// The only error type that needletail returns
@@ -65,6 +73,10 @@ impl fmt::Display for ParseError {
             ParseErrorType::InvalidRecord => "Invalid record content",
             ParseErrorType::IOError => "I/O Error",
             ParseErrorType::Invalid => "",
+            ParseErrorType::PairLengthMismatch => "Paired inputs differ in length",
+            ParseErrorType::DuplicateId => "Duplicate record id",
+            ParseErrorType::RecordTooLarge => "Record too large",
+            ParseErrorType::InvalidQuality => "Invalid quality score",
         };
         write!(f, "{}: {}", msg, self.msg)
     }
@@ -117,10 +129,188 @@ pub fn memchr_both_last(b1: u8, b2: u8, seq: &[u8]) -> Option<usize> {
     None
 }
 
+/// Match a file name against a shell-style wildcard pattern where `*`
+/// matches any run of characters and `?` matches any single character.
+pub(crate) fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    // the positions to backtrack to when the last `*` needs to match more
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+/// The id in a header: everything before the first whitespace. This is
+/// what's used to tell records apart (e.g. when deduplicating, indexing or
+/// pairing them up).
+#[inline]
+pub(crate) fn header_id(header: &[u8]) -> &[u8] {
+    header
+        .split(|c| c.is_ascii_whitespace())
+        .next()
+        .unwrap_or(b"")
+}
+
+/// The description in a header: everything after the whitespace following
+/// the first word (or `None` if there's nothing there). This is only split
+/// out when it's asked for, so parsing doesn't pay for it.
+#[inline]
+pub(crate) fn header_desc(header: &[u8]) -> Option<&[u8]> {
+    #[cfg(test)]
+    HEADER_DESC_CALLS.with(|calls| calls.set(calls.get() + 1));
+    let header = header.strip_suffix(b"\r").unwrap_or(header);
+    let end = header.iter().position(u8::is_ascii_whitespace)?;
+    let start = end
+        + header[end..]
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())?;
+    Some(&header[start..])
+}
+
+/// A 64-bit FNV-1a hash; unlike `DefaultHasher` this is guaranteed to give
+/// the same result across platforms and versions of Rust.
+#[inline]
+pub(crate) fn fnv1a_64(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in data {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// A 128-bit FNV-1a hash (see `fnv1a_64`) of a stream of bytes
+#[inline]
+pub(crate) fn fnv1a_128<I: IntoIterator<Item = u8>>(data: I) -> u128 {
+    let mut hash: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+    for b in data {
+        hash ^= u128::from(b);
+        hash = hash.wrapping_mul(0x0000_0000_0100_0000_0000_0000_0000_013b);
+    }
+    hash
+}
+
+/// A small, seedable pseudo-random number generator (SplitMix64) for
+/// reproducible sampling; not suitable for anything cryptographic.
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed float in `[0, 1)`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// How many times `header_desc` has run on this thread, so tests can
+    /// check that parsing never splits headers up on its own
+    pub(crate) static HEADER_DESC_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// A scratch directory for tests that's removed when it's dropped, so it's
+/// cleaned up even when the test fails
+#[cfg(test)]
+pub(crate) struct TestDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("needletail-{}-{}", name, std::process::id()));
+        // clear out anything left behind by an earlier run
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match(b"*.fa.gz", b"sample.part000.fa.gz"));
+        assert!(wildcard_match(
+            b"sample.part???.fa.gz",
+            b"sample.part001.fa.gz"
+        ));
+        assert!(wildcard_match(b"s*part*1*", b"sample.part001.fa.gz"));
+        assert!(wildcard_match(b"test.fa", b"test.fa"));
+        assert!(wildcard_match(b"*", b""));
+        assert!(!wildcard_match(b"*.fa.gz", b"sample.part000.fa"));
+        assert!(!wildcard_match(
+            b"sample.part??.fa.gz",
+            b"sample.part001.fa.gz"
+        ));
+        assert!(!wildcard_match(b"test.fa", b"test.fasta"));
+    }
+
+    #[test]
+    fn test_fnv1a_64() {
+        // reference values from the FNV spec
+        assert_eq!(fnv1a_64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a_64(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(
+            fnv1a_128(Vec::new()),
+            0x6c62_272e_07bb_0142_62b8_2175_6295_c58d
+        );
+        assert_eq!(
+            fnv1a_128(b"a".to_vec()),
+            0xd228_cb69_6f1a_8caf_7891_2b70_4e4a_8964
+        );
+    }
+
+    #[test]
+    fn test_splitmix64() {
+        // reference values from the original implementation
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert!((0..1000).all(|_| (0. ..1.).contains(&rng.next_f64())));
+    }
+
     #[test]
     fn test_memchr_both() {
         let pos = memchr_both(b'\n', b'-', &b"test\n-this"[..]);
@@ -141,5 +331,4 @@ mod tests {
         let pos = memchr_both_last(b'\n', b'-', &b"-te\nst\n-this"[..]);
         assert_eq!(pos, Some(6));
     }
-
 }
@@ -0,0 +1,84 @@
+//! Checks that parsing records whose sequences are on a single line doesn't
+//! allocate anything per record (the records borrow straight out of the
+//! parsing buffer).
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use needletail::parse_sequence_reader;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Parse `data` and return how many allocations were made doing it (and
+/// the number of records with borrowed sequences)
+fn count_allocations(data: &[u8]) -> (usize, usize) {
+    let data = data.to_vec();
+    let mut n_borrowed = 0;
+    let start = ALLOCATIONS.load(Ordering::SeqCst);
+    parse_sequence_reader(
+        Cursor::new(&data[..]),
+        |_| {},
+        |rec| {
+            if let Cow::Borrowed(_) = rec.seq {
+                n_borrowed += 1;
+            }
+        },
+    )
+    .unwrap();
+    (ALLOCATIONS.load(Ordering::SeqCst) - start, n_borrowed)
+}
+
+fn records(n: usize, fasta: bool, multiline: bool) -> Vec<u8> {
+    let mut data = Vec::new();
+    for i in 0..n {
+        let seq = if multiline {
+            "ACGTACGT\nACGT"
+        } else {
+            "ACGTACGTACGT"
+        };
+        let rec = if fasta {
+            format!(">read{}\n{}\n", i, seq)
+        } else {
+            format!("@read{}\n{}\n+\nIIIIIIIIIIII\n", i, seq)
+        };
+        data.extend(rec.into_bytes());
+    }
+    data
+}
+
+// everything is in one test so nothing else allocates while we're counting
+#[test]
+fn test_single_line_records_dont_allocate() {
+    // the first parse can allocate for one-time setup
+    count_allocations(&records(10, true, false));
+
+    for fasta in &[true, false] {
+        let (few, _) = count_allocations(&records(10, *fasta, false));
+        let (many, n_borrowed) = count_allocations(&records(1000, *fasta, false));
+        assert_eq!(few, many);
+        assert_eq!(n_borrowed, 1000);
+    }
+
+    // but multi-line FASTA sequences have to be copied to strip them
+    let (few, _) = count_allocations(&records(10, true, true));
+    let (many, n_borrowed) = count_allocations(&records(1000, true, true));
+    assert!(many >= few + 990);
+    assert_eq!(n_borrowed, 0);
+}